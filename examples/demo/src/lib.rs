@@ -24,6 +24,7 @@ use bevy_oxr::{
     xr_init::xr_only,
     xr_input::{
         actions::XrActionSets,
+        controllers::XrControllers,
         debug_gizmos::OpenXrDebugRenderer,
         hands::common::{HandInputDebugRenderer, HandResource, HandsResource},
         hands::HandBone,
@@ -32,7 +33,6 @@ use bevy_oxr::{
             update_interactable_states, InteractionEvent, Touched, XRDirectInteractor,
             XRInteractable, XRInteractableState, XRInteractorState, XRSelection,
         },
-        oculus_touch::OculusController,
         prototype_locomotion::{proto_locomotion, PrototypeLocomotionConfig},
         trackers::{OpenXRController, OpenXRLeftController, OpenXRRightController, OpenXRTracker},
         Hand,
@@ -521,7 +521,7 @@ pub struct SpawnCubeRequest;
 pub struct SpawnCubeTimer(Timer);
 
 fn request_cube_spawn(
-    oculus_controller: Res<OculusController>,
+    controllers: Res<XrControllers>,
     frame_state: Res<XrFrameState>,
     xr_input: Res<XrInput>,
     session: Res<XrSession>,
@@ -533,14 +533,14 @@ fn request_cube_spawn(
     timer.0.tick(time.delta());
     if timer.0.finished() {
         //get controller
-        let controller = oculus_controller.get_ref(&session, &frame_state, &xr_input, &action_sets);
+        let controller = controllers.get_ref(&session, &frame_state, &xr_input, &action_sets);
         //get controller triggers
-        let left_main_button = controller.a_button();
+        let left_main_button = controller.primary_button(Hand::Right);
         if left_main_button {
             writer.send(SpawnCubeRequest::default());
             timer.0.reset();
         }
-        let right_main_button = controller.x_button();
+        let right_main_button = controller.primary_button(Hand::Left);
         if right_main_button {
             writer.send(SpawnCubeRequest::default());
             timer.0.reset();
@@ -576,7 +576,7 @@ fn cube_spawner(
 
 //TODO: find a real place for this
 fn prototype_interaction_input(
-    oculus_controller: Res<OculusController>,
+    controllers: Res<XrControllers>,
     frame_state: Res<XrFrameState>,
     xr_input: Res<XrInput>,
     session: Res<XrSession>,
@@ -599,7 +599,7 @@ fn prototype_interaction_input(
     action_sets: Res<XrActionSets>,
 ) {
     //get controller
-    let controller = oculus_controller.get_ref(&session, &frame_state, &xr_input, &action_sets);
+    let controller = controllers.get_ref(&session, &frame_state, &xr_input, &action_sets);
     //get controller triggers
     let left_trigger = controller.trigger(Hand::Left);
     let right_trigger = controller.trigger(Hand::Right);
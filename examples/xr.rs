@@ -8,13 +8,13 @@ use bevy_oxr::resources::{XrFrameState, XrSession};
 
 use bevy_oxr::xr_init::{xr_only, EndXrSession, StartXrSession, XrSetup};
 use bevy_oxr::xr_input::actions::XrActionSets;
+use bevy_oxr::xr_input::controllers::XrControllers;
 use bevy_oxr::xr_input::hands::common::HandInputDebugRenderer;
 use bevy_oxr::xr_input::interactions::{
     draw_interaction_gizmos, draw_socket_gizmos, interactions, socket_interactions,
     update_interactable_states, InteractionEvent, Touched, XRDirectInteractor, XRInteractable,
     XRInteractableState, XRInteractorState, XRRayInteractor, XRSocketInteractor,
 };
-use bevy_oxr::xr_input::oculus_touch::OculusController;
 use bevy_oxr::xr_input::prototype_locomotion::{proto_locomotion, PrototypeLocomotionConfig};
 use bevy_oxr::xr_input::trackers::{
     AimPose, OpenXRController, OpenXRLeftController, OpenXRRightController, OpenXRTracker,
@@ -172,7 +172,7 @@ fn spawn_controllers_example(mut commands: Commands) {
 
 #[allow(clippy::type_complexity)]
 fn prototype_interaction_input(
-    oculus_controller: Res<OculusController>,
+    controllers: Res<XrControllers>,
     frame_state: Res<XrFrameState>,
     xr_input: Res<XrInput>,
     session: Res<XrSession>,
@@ -195,7 +195,7 @@ fn prototype_interaction_input(
     action_sets: Res<XrActionSets>,
 ) {
     //get controller
-    let controller = oculus_controller.get_ref(&session, &frame_state, &xr_input, &action_sets);
+    let controller = controllers.get_ref(&session, &frame_state, &xr_input, &action_sets);
     //get controller triggers
     let left_trigger = controller.trigger(Hand::Left);
     let right_trigger = controller.trigger(Hand::Right);
@@ -26,6 +26,38 @@ impl XrExtensions {
         self.0.ext_hand_tracking = false;
         self
     }
+    pub fn enable_hand_tracking_mesh(&mut self) -> &mut Self {
+        self.0.fb_hand_tracking_mesh = true;
+        self
+    }
+    pub fn disable_hand_tracking_mesh(&mut self) -> &mut Self {
+        self.0.fb_hand_tracking_mesh = false;
+        self
+    }
+    pub fn enable_hand_tracking_aim(&mut self) -> &mut Self {
+        self.0.fb_hand_tracking_aim = true;
+        self
+    }
+    pub fn disable_hand_tracking_aim(&mut self) -> &mut Self {
+        self.0.fb_hand_tracking_aim = false;
+        self
+    }
+    pub fn enable_hand_tracking_capsules(&mut self) -> &mut Self {
+        self.0.fb_hand_tracking_capsules = true;
+        self
+    }
+    pub fn disable_hand_tracking_capsules(&mut self) -> &mut Self {
+        self.0.fb_hand_tracking_capsules = false;
+        self
+    }
+    pub fn enable_hand_joints_motion_range(&mut self) -> &mut Self {
+        self.0.ext_hand_joints_motion_range = true;
+        self
+    }
+    pub fn disable_hand_joints_motion_range(&mut self) -> &mut Self {
+        self.0.ext_hand_joints_motion_range = false;
+        self
+    }
     pub fn enable_local_floor(&mut self) -> &mut Self {
         self.0.ext_local_floor = true;
         self
@@ -34,6 +66,65 @@ impl XrExtensions {
         self.0.ext_local_floor = false;
         self
     }
+    /// also enables `XR_KHR_binding_modification` which is required for dpad bindings
+    pub fn enable_dpad_binding(&mut self) -> &mut Self {
+        self.0.ext_dpad_binding = true;
+        self.0.khr_binding_modification = true;
+        self
+    }
+    pub fn disable_dpad_binding(&mut self) -> &mut Self {
+        self.0.ext_dpad_binding = false;
+        self
+    }
+    pub fn enable_palm_pose(&mut self) -> &mut Self {
+        self.0.ext_palm_pose = true;
+        self
+    }
+    pub fn disable_palm_pose(&mut self) -> &mut Self {
+        self.0.ext_palm_pose = false;
+        self
+    }
+    pub fn enable_vive_trackers(&mut self) -> &mut Self {
+        self.0.htcx_vive_tracker_interaction = true;
+        self
+    }
+    pub fn disable_vive_trackers(&mut self) -> &mut Self {
+        self.0.htcx_vive_tracker_interaction = false;
+        self
+    }
+    pub fn enable_vive_wrist_trackers(&mut self) -> &mut Self {
+        self.0.htc_vive_wrist_tracker_interaction = true;
+        self
+    }
+    pub fn disable_vive_wrist_trackers(&mut self) -> &mut Self {
+        self.0.htc_vive_wrist_tracker_interaction = false;
+        self
+    }
+    pub fn enable_eye_gaze_interaction(&mut self) -> &mut Self {
+        self.0.ext_eye_gaze_interaction = true;
+        self
+    }
+    pub fn disable_eye_gaze_interaction(&mut self) -> &mut Self {
+        self.0.ext_eye_gaze_interaction = false;
+        self
+    }
+    /// makes the `/interaction_profiles/hp/mixed_reality_controller` profile available
+    pub fn enable_hp_mixed_reality_controller(&mut self) -> &mut Self {
+        self.0.ext_hp_mixed_reality_controller = true;
+        self
+    }
+    pub fn disable_hp_mixed_reality_controller(&mut self) -> &mut Self {
+        self.0.ext_hp_mixed_reality_controller = false;
+        self
+    }
+    pub fn enable_facial_tracking(&mut self) -> &mut Self {
+        self.0.htc_facial_tracking = true;
+        self
+    }
+    pub fn disable_facial_tracking(&mut self) -> &mut Self {
+        self.0.htc_facial_tracking = false;
+        self
+    }
 }
 impl From<ExtensionSet> for XrExtensions {
     fn from(value: ExtensionSet) -> Self {
@@ -132,8 +223,8 @@ impl ops::BitAnd for XrExtensions {
         out.htc_hand_interaction = self.0.htc_hand_interaction && rhs.0.htc_hand_interaction;
         out.htc_vive_wrist_tracker_interaction =
             self.0.htc_vive_wrist_tracker_interaction && rhs.0.htc_vive_wrist_tracker_interaction;
-        // out.htcx_vive_tracker_interaction =
-        //     self.0.htcx_vive_tracker_interaction && rhs.0.htcx_vive_tracker_interaction;
+        out.htcx_vive_tracker_interaction =
+            self.0.htcx_vive_tracker_interaction && rhs.0.htcx_vive_tracker_interaction;
         out.huawei_controller_interaction =
             self.0.huawei_controller_interaction && rhs.0.huawei_controller_interaction;
         out.khr_composition_layer_cube =
@@ -33,9 +33,25 @@ use xr_init::{
     XrHasWaited, XrPostCleanup, XrShouldRender, XrStatus, XrInitPlugin,
 };
 use xr_input::actions::XrActionsPlugin;
+use xr_input::bevy_input::XrBevyInputPlugin;
+use xr_input::eye_gaze::XrEyeGazePlugin;
+use xr_input::facial_tracking::XrFacialTrackingPlugin;
+use xr_input::hands::data_source::HandDataSourcePlugin;
 use xr_input::hands::emulated::HandEmulationPlugin;
+use xr_input::hands::gestures::HandGesturePlugin;
+use xr_input::hands::hand_aim::HandAimPlugin;
+use xr_input::hands::hand_capsules::HandCapsulePlugin;
+use xr_input::hands::hand_mesh::HandMeshPlugin;
+use xr_input::hands::hand_pose::HandPosePlugin;
+use xr_input::hands::hand_rig::HandRigPlugin;
 use xr_input::hands::hand_tracking::HandTrackingPlugin;
 use xr_input::hands::HandPlugin;
+use xr_input::haptics::XrHapticsPlugin;
+use xr_input::interaction_profiles::{
+    update_interaction_profiles, XrInteractionProfileChanged, XrInteractionProfiles,
+};
+use xr_input::late_latch::{LateLatchPlugin, LateLatchSpaces};
+use xr_input::vive_trackers::{ViveTrackerPlugin, XrViveTrackerConnected};
 use xr_input::xr_camera::XrCameraPlugin;
 use xr_input::XrInputPlugin;
 use crate::xr_init::StartXrSession;
@@ -60,6 +76,9 @@ impl Plugin for OpenXrPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(XrSessionRunning::new(AtomicBool::new(false)));
         app.insert_resource(ExitAppOnSessionExit::default());
+        app.init_resource::<XrInteractionProfiles>();
+        app.add_event::<XrInteractionProfileChanged>();
+        app.add_event::<XrViveTrackerConnected>();
         #[cfg(not(target_arch = "wasm32"))]
         match graphics::initialize_xr_instance(
             &self.backend_preference,
@@ -196,6 +215,7 @@ fn clean_resources_render(cmds: &mut World) {
     cmds.remove_resource::<XrInput>();
     cmds.remove_resource::<XrViews>();
     cmds.remove_resource::<XrFrameState>();
+    cmds.remove_resource::<LateLatchSpaces>();
     cmds.remove_resource::<CleanupRenderWorld>();
     // unsafe {
     //     (session.instance().fp().destroy_session)(session.as_raw());
@@ -304,11 +324,24 @@ impl PluginGroup for DefaultXrPlugins {
             .add_after::<OpenXrPlugin, _>(XrInitPlugin)
             .add(XrInputPlugin)
             .add(XrActionsPlugin)
+            .add(XrHapticsPlugin)
+            .add(XrBevyInputPlugin)
             .add(XrCameraPlugin)
+            .add(LateLatchPlugin)
             .add_before::<OpenXrPlugin, _>(XrEarlyInitPlugin)
             .add(HandPlugin)
             .add(HandTrackingPlugin)
             .add(HandEmulationPlugin)
+            .add(HandDataSourcePlugin)
+            .add(HandGesturePlugin)
+            .add(HandAimPlugin)
+            .add(HandCapsulePlugin)
+            .add(HandMeshPlugin)
+            .add(HandRigPlugin)
+            .add(HandPosePlugin)
+            .add(XrEyeGazePlugin)
+            .add(ViveTrackerPlugin)
+            .add(XrFacialTrackingPlugin)
             .add(PassthroughPlugin)
             .add(XrResourcePlugin)
             .add(StartSessionOnStartup)
@@ -343,6 +376,7 @@ fn xr_reset_per_frame_resources(
     **waited = false;
 }
 
+#[allow(clippy::too_many_arguments)]
 fn xr_poll_events(
     instance: Option<Res<XrInstance>>,
     session: Option<Res<XrSession>>,
@@ -353,10 +387,14 @@ fn xr_poll_events(
     mut setup_xr: EventWriter<SetupXrData>,
     mut cleanup_xr: EventWriter<CleanupXrData>,
     mut events: NonSendMut<XrEvents>,
+    mut interaction_profiles: ResMut<XrInteractionProfiles>,
+    mut interaction_profile_changed: EventWriter<XrInteractionProfileChanged>,
+    mut vive_tracker_connected: EventWriter<XrViveTrackerConnected>,
 ) {
     if let (Some(instance), Some(session)) = (instance, session) {
         let _span = info_span!("xr_poll_events");
         let mut new_events = Vec::new();
+        let mut profile_changed = false;
         loop {
             let mut evt_buf = Box::new(xr::EventDataBuffer::default());
             if let Some(event) = instance.poll_event(evt_buf.as_mut()).unwrap() {
@@ -403,6 +441,12 @@ fn xr_poll_events(
                     EventsLost(e) => {
                         warn!("lost {} XR events", e.lost_event_count());
                     }
+                    InteractionProfileChanged(_) => {
+                        profile_changed = true;
+                    }
+                    ViveTrackerConnectedHTCX(_) => {
+                        vive_tracker_connected.send_default();
+                    }
                     _ => {}
                 }
                 new_events.push(evt_buf)
@@ -410,6 +454,14 @@ fn xr_poll_events(
                 break;
             }
         }
+        if profile_changed {
+            update_interaction_profiles(
+                &instance,
+                &session,
+                &mut interaction_profiles,
+                &mut interaction_profile_changed,
+            );
+        }
 
         *events = XrEvents(new_events);
     }
@@ -554,3 +606,13 @@ pub fn locate_views(
         }
     }
 }
+
+/// Turns a raw `XrResult` into a [`xr::Result`], for calling extension functions through their
+/// function pointers
+pub(crate) fn cvt(x: xr::sys::Result) -> xr::Result<xr::sys::Result> {
+    if x.into_raw() >= 0 {
+        Ok(x)
+    } else {
+        Err(x)
+    }
+}
@@ -4,6 +4,7 @@ use std::{marker::PhantomData, mem, ptr};
 
 use crate::resources::XrSession;
 use crate::{
+    cvt,
     resources::XrInstance,
     xr_arc_resource_wrapper,
     xr_init::{XrCleanup, XrSetup},
@@ -130,14 +131,6 @@ pub struct ResumePassthrough;
 #[derive(Clone, Copy, Debug, Default, Reflect, Event)]
 pub struct PausePassthrough;
 
-fn cvt(x: xr::sys::Result) -> xr::Result<xr::sys::Result> {
-    if x.into_raw() >= 0 {
-        Ok(x)
-    } else {
-        Err(x)
-    }
-}
-
 #[derive(Copy, Clone)]
 #[repr(transparent)]
 pub(crate) struct CompositionLayerPassthrough<'a, G: xr::Graphics> {
@@ -1,10 +1,13 @@
 use std::error::Error;
+use std::ffi::c_void;
+use std::ptr;
 
 use bevy::{prelude::*, utils::HashMap};
 use openxr as xr;
 use xr::{Action, Binding, Haptic, Posef, Vector2f};
 
 use crate::{
+    cvt,
     resources::{XrInstance, XrSession},
     xr_init::{xr_only, XrCleanup, XrPrePostSetup, XrPreSetup},
 };
@@ -61,6 +64,8 @@ pub fn setup_oxr_actions(world: &mut World) {
     let left_path = instance.string_to_path("/user/hand/left").unwrap();
     let right_path = instance.string_to_path("/user/hand/right").unwrap();
     let hands = [left_path, right_path];
+    let dpad_supported = instance.exts().ext_dpad_binding.is_some()
+        && instance.exts().khr_binding_modification.is_some();
 
     let mut action_sets = XrActionSets { sets: default() };
     // let mut action_bindings: HashMap<&'static str, Vec<xr::Path>> = HashMap::new();
@@ -68,6 +73,7 @@ pub fn setup_oxr_actions(world: &mut World) {
         (&'static str, &'static str),
         HashMap<&'static str, Vec<xr::Path>>,
     > = HashMap::new();
+    let mut dpad_bindings: HashMap<&'static str, Vec<DpadBindingModification>> = HashMap::new();
     for (set_name, set) in actions.sets.into_iter() {
         let mut actions: HashMap<&'static str, TypedAction> = default();
         let oxr_action_set = instance
@@ -95,6 +101,13 @@ pub fn setup_oxr_actions(world: &mut World) {
             actions.insert(action_name, typed_action);
             for (device_path, bindings) in action.bindings.into_iter() {
                 for b in bindings {
+                    if !dpad_supported && dpad_identifier(b).is_some() {
+                        warn!(
+                            "dpad bindings are not supported, not binding {} to {}",
+                            action_name, b
+                        );
+                        continue;
+                    }
                     // info!("binding {} to {}", action_name, b);
                     action_bindings
                         .entry((set_name, action_name))
@@ -105,6 +118,20 @@ pub fn setup_oxr_actions(world: &mut World) {
                 }
             }
         }
+        if dpad_supported {
+            for (device_path, dpads) in set.dpad_bindings.into_iter() {
+                for (identifier, settings) in dpads {
+                    dpad_bindings
+                        .entry(device_path)
+                        .or_default()
+                        .push(DpadBindingModification {
+                            identifier: instance.string_to_path(identifier).unwrap(),
+                            action_set: oxr_action_set.as_raw(),
+                            settings,
+                        });
+                }
+            }
+        }
         // oxr_action_sets.push(oxr_action_set);
         action_sets.sets.insert(
             set_name,
@@ -115,7 +142,7 @@ pub fn setup_oxr_actions(world: &mut World) {
             },
         );
     }
-    let mut b_indings: HashMap<&'static str, Vec<Binding>> = HashMap::new();
+    let mut b_indings: HashMap<&'static str, Vec<(&TypedAction, xr::Path)>> = HashMap::new();
     for (dev, mut bindings) in action_sets
         .sets
         .iter()
@@ -128,8 +155,8 @@ pub fn setup_oxr_actions(world: &mut World) {
         .flat_map(move |((set_name, action_name, action), bindings)| {
             bindings
                 .get(&(set_name as &'static str, action_name as &'static str))
-                .unwrap()
-                .iter()
+                .into_iter()
+                .flatten()
                 .map(move |(dev, bindings)| (action, dev, bindings))
         })
         .map(|(action, dev, bindings)| {
@@ -137,13 +164,7 @@ pub fn setup_oxr_actions(world: &mut World) {
                 dev,
                 bindings
                     .iter()
-                    .map(move |binding| match &action {
-                        TypedAction::Vec2(a) => Binding::new(a, *binding),
-                        TypedAction::F32(a) => Binding::new(a, *binding),
-                        TypedAction::Bool(a) => Binding::new(a, *binding),
-                        TypedAction::PoseF(a) => Binding::new(a, *binding),
-                        TypedAction::Haptic(a) => Binding::new(a, *binding),
-                    })
+                    .map(move |binding| (action, *binding))
                     .collect::<Vec<_>>(),
             )
         })
@@ -151,9 +172,18 @@ pub fn setup_oxr_actions(world: &mut World) {
         b_indings.entry(dev).or_default().append(&mut bindings);
     }
     for (dev, bindings) in b_indings.into_iter() {
-        instance
-            .suggest_interaction_profile_bindings(instance.string_to_path(dev).unwrap(), &bindings)
-            .expect("Unable to suggest interaction bindings!");
+        let profile = instance.string_to_path(dev).unwrap();
+        match dpad_bindings.get(dev) {
+            Some(dpads) => suggest_bindings_with_dpads(instance, profile, &bindings, dpads),
+            None => instance.suggest_interaction_profile_bindings(
+                profile,
+                &bindings
+                    .iter()
+                    .map(|(action, binding)| action.binding(*binding))
+                    .collect::<Vec<_>>(),
+            ),
+        }
+        .expect("Unable to suggest interaction bindings!");
     }
     session
         .attach_action_sets(
@@ -168,6 +198,80 @@ pub fn setup_oxr_actions(world: &mut World) {
     world.insert_resource(action_sets);
 }
 
+/// Returns the dpad identifier path of a dpad binding path,
+/// e.g. `/user/hand/left/input/thumbstick` for `/user/hand/left/input/thumbstick/dpad_up`
+fn dpad_identifier(binding_path: &'static str) -> Option<&'static str> {
+    let (identifier, component) = binding_path.rsplit_once('/')?;
+    matches!(
+        component,
+        "dpad_up" | "dpad_down" | "dpad_left" | "dpad_right" | "dpad_center"
+    )
+    .then_some(identifier)
+}
+
+struct DpadBindingModification {
+    identifier: xr::Path,
+    action_set: xr::sys::ActionSet,
+    settings: DpadBindingSettings,
+}
+
+/// `xrSuggestInteractionProfileBindings` with `XrInteractionProfileDpadBindingEXT`s chained
+/// through `XrBindingModificationsKHR`, openxrs doesn't expose the next chain for this
+fn suggest_bindings_with_dpads(
+    instance: &XrInstance,
+    profile: xr::Path,
+    bindings: &[(&TypedAction, xr::Path)],
+    dpads: &[DpadBindingModification],
+) -> xr::Result<()> {
+    let raw_bindings = bindings
+        .iter()
+        .map(|(action, binding)| xr::sys::ActionSuggestedBinding {
+            action: action.as_raw(),
+            binding: *binding,
+        })
+        .collect::<Vec<_>>();
+    let dpad_infos = dpads
+        .iter()
+        .map(|dpad| xr::sys::InteractionProfileDpadBindingEXT {
+            ty: xr::sys::InteractionProfileDpadBindingEXT::TYPE,
+            next: ptr::null(),
+            binding: dpad.identifier,
+            action_set: dpad.action_set,
+            force_threshold: dpad.settings.force_threshold,
+            force_threshold_released: dpad.settings.force_threshold_released,
+            center_region: dpad.settings.center_region,
+            wedge_angle: dpad.settings.wedge_angle,
+            is_sticky: dpad.settings.is_sticky.into(),
+            on_haptic: ptr::null(),
+            off_haptic: ptr::null(),
+        })
+        .collect::<Vec<_>>();
+    let modification_ptrs = dpad_infos
+        .iter()
+        .map(|info| info as *const _ as *const xr::sys::BindingModificationBaseHeaderKHR)
+        .collect::<Vec<_>>();
+    let modifications = xr::sys::BindingModificationsKHR {
+        ty: xr::sys::BindingModificationsKHR::TYPE,
+        next: ptr::null(),
+        binding_modification_count: modification_ptrs.len() as u32,
+        binding_modifications: modification_ptrs.as_ptr(),
+    };
+    let suggested_bindings = xr::sys::InteractionProfileSuggestedBinding {
+        ty: xr::sys::InteractionProfileSuggestedBinding::TYPE,
+        next: &modifications as *const _ as *const c_void,
+        interaction_profile: profile,
+        count_suggested_bindings: raw_bindings.len() as u32,
+        suggested_bindings: raw_bindings.as_ptr(),
+    };
+    unsafe {
+        cvt((instance.fp().suggest_interaction_profile_bindings)(
+            instance.as_raw(),
+            &suggested_bindings,
+        ))?;
+    }
+    Ok(())
+}
+
 pub enum ActionHandednes {
     Single,
     Double,
@@ -190,6 +294,27 @@ pub enum TypedAction {
     Vec2(Action<Vector2f>),
 }
 
+impl TypedAction {
+    fn binding(&self, path: xr::Path) -> Binding {
+        match self {
+            TypedAction::Vec2(a) => Binding::new(a, path),
+            TypedAction::F32(a) => Binding::new(a, path),
+            TypedAction::Bool(a) => Binding::new(a, path),
+            TypedAction::PoseF(a) => Binding::new(a, path),
+            TypedAction::Haptic(a) => Binding::new(a, path),
+        }
+    }
+    fn as_raw(&self) -> xr::sys::Action {
+        match self {
+            TypedAction::Vec2(a) => a.as_raw(),
+            TypedAction::F32(a) => a.as_raw(),
+            TypedAction::Bool(a) => a.as_raw(),
+            TypedAction::PoseF(a) => a.as_raw(),
+            TypedAction::Haptic(a) => a.as_raw(),
+        }
+    }
+}
+
 pub struct SetupAction {
     pretty_name: String,
     action_type: ActionType,
@@ -201,6 +326,37 @@ pub struct SetupActionSet {
     pretty_name: String,
     priority: u32,
     actions: HashMap<&'static str, SetupAction>,
+    dpad_bindings: HashMap<&'static str, Vec<(&'static str, DpadBindingSettings)>>,
+}
+
+/// Settings for turning a thumbstick or trackpad into a dpad through `XR_EXT_dpad_binding`
+///
+/// Requires the `XR_EXT_dpad_binding` and `XR_KHR_binding_modification` extensions,
+/// see `XrExtensions::enable_dpad_binding`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DpadBindingSettings {
+    /// how far the stick has to be pushed for a direction to become active
+    pub force_threshold: f32,
+    /// how far the stick has to return for a direction to become inactive again
+    pub force_threshold_released: f32,
+    /// radius of the center region, `dpad_center` is active when the stick is inside of it
+    pub center_region: f32,
+    /// angle in radians of each direction wedge
+    pub wedge_angle: f32,
+    /// keeps the first direction active until the stick is released
+    pub is_sticky: bool,
+}
+
+impl Default for DpadBindingSettings {
+    fn default() -> Self {
+        Self {
+            force_threshold: 0.5,
+            force_threshold_released: 0.4,
+            center_region: 0.5,
+            wedge_angle: std::f32::consts::FRAC_PI_2,
+            is_sticky: false,
+        }
+    }
 }
 
 impl SetupActionSet {
@@ -233,6 +389,28 @@ impl SetupActionSet {
                 .push(binding.path);
         }
     }
+    /// Suggests bindings to dpad paths like `/user/hand/left/input/thumbstick/dpad_up`,
+    /// every thumbstick or trackpad used by `bindings` gets `settings` applied.
+    ///
+    /// The bindings are skipped when the runtime doesn't support `XR_EXT_dpad_binding`
+    pub fn suggest_dpad_binding(
+        &mut self,
+        device_path: &'static str,
+        bindings: &[XrBinding],
+        settings: DpadBindingSettings,
+    ) {
+        self.suggest_binding(device_path, bindings);
+        for binding in bindings {
+            let identifier = dpad_identifier(binding.path)
+                .ok_or(eyre::eyre!("Not a dpad binding: {}", binding.path))
+                .unwrap();
+            let dpads = self.dpad_bindings.entry(device_path).or_default();
+            match dpads.iter_mut().find(|(i, _)| *i == identifier) {
+                Some((_, s)) => *s = settings,
+                None => dpads.push((identifier, settings)),
+            }
+        }
+    }
 }
 pub struct XrBinding {
     action: &'static str,
@@ -266,6 +444,7 @@ impl SetupActionSets {
                 pretty_name,
                 priority,
                 actions: HashMap::new(),
+                dpad_bindings: HashMap::new(),
             },
         );
         self.sets.get_mut(name).unwrap()
@@ -291,6 +470,7 @@ impl FmtDisplay for ActionError {
             ActionError::NoActionSet => "Action Set Not Found!",
             ActionError::NoAction => "Action Not Found!",
             ActionError::WrongActionType => "Wrong Action Type!",
+            ActionError::OpenXr(err) => return write!(f, "OpenXR Error: {}", err),
         };
         write!(f, "{}", err)
     }
@@ -301,9 +481,75 @@ pub enum ActionError {
     NoActionSet,
     NoAction,
     WrongActionType,
+    OpenXr(xr::sys::Result),
+}
+
+impl From<xr::sys::Result> for ActionError {
+    fn from(value: xr::sys::Result) -> Self {
+        ActionError::OpenXr(value)
+    }
+}
+
+impl TypedAction {
+    fn enumerate_bound_sources(
+        &self,
+        session: &xr::Session<xr::AnyGraphics>,
+    ) -> xr::Result<Vec<xr::Path>> {
+        match self {
+            TypedAction::F32(a) => session.enumerate_bound_sources(a),
+            TypedAction::Bool(a) => session.enumerate_bound_sources(a),
+            TypedAction::PoseF(a) => session.enumerate_bound_sources(a),
+            TypedAction::Haptic(a) => session.enumerate_bound_sources(a),
+            TypedAction::Vec2(a) => session.enumerate_bound_sources(a),
+        }
+    }
 }
 
 impl XrActionSets {
+    /// The physical input sources an action is currently bound to, e.g.
+    /// `/user/hand/right/input/trigger/value`.
+    ///
+    /// Only valid after the first [`sync_actions`] and can change every time the interaction
+    /// profile changes.
+    pub fn bound_sources(
+        &self,
+        session: &XrSession,
+        action_set: &'static str,
+        action_name: &'static str,
+    ) -> Result<Vec<xr::Path>, ActionError> {
+        let action = self
+            .sets
+            .get(action_set)
+            .ok_or(ActionError::NoActionSet)?
+            .actions
+            .get(action_name)
+            .ok_or(ActionError::NoAction)?;
+        Ok(action.enumerate_bound_sources(session)?)
+    }
+    /// Localized, human readable name of a source returned by [`XrActionSets::bound_sources`].
+    ///
+    /// `components` selects the parts of the name, for example `USER_PATH | INTERACTION_PROFILE
+    /// | COMPONENT` gives "Left Hand Oculus Touch Controller Trigger".
+    pub fn localized_source_name(
+        session: &XrSession,
+        source: xr::Path,
+        components: xr::InputSourceLocalizedNameFlags,
+    ) -> Result<String, ActionError> {
+        Ok(session.input_source_localized_name(source, components)?)
+    }
+    /// [`XrActionSets::bound_sources`] mapped through [`XrActionSets::localized_source_name`]
+    pub fn bound_source_names(
+        &self,
+        session: &XrSession,
+        action_set: &'static str,
+        action_name: &'static str,
+        components: xr::InputSourceLocalizedNameFlags,
+    ) -> Result<Vec<String>, ActionError> {
+        self.bound_sources(session, action_set, action_name)?
+            .into_iter()
+            .map(|source| Self::localized_source_name(session, source, components))
+            .collect()
+    }
     pub fn get_action_vec2(
         &self,
         action_set: &'static str,
@@ -0,0 +1,177 @@
+use bevy::prelude::*;
+
+use crate::{
+    input::XrInput,
+    resources::{XrFrameState, XrSession},
+    xr_init::{xr_only, XrCleanup},
+};
+
+use super::{
+    actions::{sync_actions, XrActionSets},
+    controllers::XrControllers,
+    Hand,
+};
+
+/// Mirrors the controller state into [`ButtonInput<XrButton>`] and [`Axis<XrAxis>`],
+/// the same way bevy exposes gamepads
+pub struct XrBevyInputPlugin;
+
+impl Plugin for XrBevyInputPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ButtonInput<XrButton>>();
+        app.init_resource::<Axis<XrAxis>>();
+        app.init_resource::<XrButtonSettings>();
+        app.add_systems(
+            PreUpdate,
+            update_xr_bevy_input.after(sync_actions).run_if(xr_only()),
+        );
+        app.add_systems(XrCleanup, reset_xr_bevy_input);
+    }
+}
+
+/// `X`/`A` are the primary and `Y`/`B` the secondary buttons of the left/right controller
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum XrButtonType {
+    Trigger,
+    Grip,
+    A,
+    B,
+    X,
+    Y,
+    Menu,
+    ThumbstickClick,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct XrButton {
+    pub hand: Hand,
+    pub button_type: XrButtonType,
+}
+
+impl XrButton {
+    pub fn new(hand: Hand, button_type: XrButtonType) -> Self {
+        Self { hand, button_type }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum XrAxisType {
+    Trigger,
+    Squeeze,
+    ThumbstickX,
+    ThumbstickY,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct XrAxis {
+    pub hand: Hand,
+    pub axis_type: XrAxisType,
+}
+
+impl XrAxis {
+    pub fn new(hand: Hand, axis_type: XrAxisType) -> Self {
+        Self { hand, axis_type }
+    }
+}
+
+/// Thresholds for turning the analog trigger and grip into [`XrButton`]s
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct XrButtonSettings {
+    pub press_threshold: f32,
+    pub release_threshold: f32,
+}
+
+impl Default for XrButtonSettings {
+    fn default() -> Self {
+        Self {
+            press_threshold: 0.75,
+            release_threshold: 0.65,
+        }
+    }
+}
+
+impl XrButtonSettings {
+    fn is_pressed(&self, value: f32, was_pressed: bool) -> bool {
+        match was_pressed {
+            true => value > self.release_threshold,
+            false => value >= self.press_threshold,
+        }
+    }
+}
+
+fn set_button(buttons: &mut ButtonInput<XrButton>, button: XrButton, pressed: bool) {
+    match (pressed, buttons.pressed(button)) {
+        (true, false) => buttons.press(button),
+        (false, true) => buttons.release(button),
+        _ => {}
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn update_xr_bevy_input(
+    controllers: Res<XrControllers>,
+    frame_state: Res<XrFrameState>,
+    xr_input: Res<XrInput>,
+    session: Res<XrSession>,
+    action_sets: Res<XrActionSets>,
+    settings: Res<XrButtonSettings>,
+    mut buttons: ResMut<ButtonInput<XrButton>>,
+    mut axes: ResMut<Axis<XrAxis>>,
+) {
+    let controller = controllers.get_ref(&session, &frame_state, &xr_input, &action_sets);
+    buttons.bypass_change_detection().clear();
+    for hand in [Hand::Left, Hand::Right] {
+        let trigger = controller.trigger(hand);
+        let squeeze = controller.squeeze(hand);
+        let stick = controller.thumbstick(hand);
+        axes.set(XrAxis::new(hand, XrAxisType::Trigger), trigger);
+        axes.set(XrAxis::new(hand, XrAxisType::Squeeze), squeeze);
+        axes.set(XrAxis::new(hand, XrAxisType::ThumbstickX), stick.x);
+        axes.set(XrAxis::new(hand, XrAxisType::ThumbstickY), stick.y);
+
+        let trigger_button = XrButton::new(hand, XrButtonType::Trigger);
+        let pressed = settings.is_pressed(trigger, buttons.pressed(trigger_button));
+        set_button(&mut buttons, trigger_button, pressed);
+        let grip_button = XrButton::new(hand, XrButtonType::Grip);
+        let pressed = settings.is_pressed(squeeze, buttons.pressed(grip_button));
+        set_button(&mut buttons, grip_button, pressed);
+        set_button(
+            &mut buttons,
+            XrButton::new(hand, XrButtonType::ThumbstickClick),
+            stick.click,
+        );
+        set_button(
+            &mut buttons,
+            XrButton::new(hand, XrButtonType::Menu),
+            controller.menu_button(hand),
+        );
+        let (primary, secondary) = match hand {
+            Hand::Left => (XrButtonType::X, XrButtonType::Y),
+            Hand::Right => (XrButtonType::A, XrButtonType::B),
+        };
+        set_button(
+            &mut buttons,
+            XrButton::new(hand, primary),
+            controller.primary_button(hand),
+        );
+        set_button(
+            &mut buttons,
+            XrButton::new(hand, secondary),
+            controller.secondary_button(hand),
+        );
+    }
+}
+
+fn reset_xr_bevy_input(mut buttons: ResMut<ButtonInput<XrButton>>, mut axes: ResMut<Axis<XrAxis>>) {
+    buttons.reset_all();
+    for hand in [Hand::Left, Hand::Right] {
+        for axis_type in [
+            XrAxisType::Trigger,
+            XrAxisType::Squeeze,
+            XrAxisType::ThumbstickX,
+            XrAxisType::ThumbstickY,
+        ] {
+            axes.remove(XrAxis::new(hand, axis_type));
+        }
+    }
+}
@@ -1,10 +1,526 @@
-use openxr::{Action, ActionTy};
+use bevy::prelude::{
+    default, Commands, Component, Entity, Has, Or, Query, Res, ResMut, Resource, With, Without,
+};
+use openxr::{
+    Action, ActionTy, AnyGraphics, FrameState, Posef, Session, Space, SpaceLocation, SpaceVelocity,
+};
+
+use crate::input::XrInput;
+use crate::resources::{XrFrameState, XrInstance, XrSession};
+
+use super::actions::{ActionHandednes, ActionType, SetupActionSets, XrActionSets, XrBinding};
+use super::oculus_touch::{subaction_path, Thumbstick};
+use super::trackers::{OpenXRLeftController, OpenXRRightController, XrVelocity};
+use super::Hand;
 
 pub struct Touchable<T: ActionTy> {
     pub inner: Action<T>,
     pub touch: Action<bool>,
 }
+#[derive(Clone, Default)]
 pub struct Handed<T> {
     pub left: T,
     pub right: T,
 }
+
+impl<T> Handed<T> {
+    pub fn get(&self, hand: Hand) -> &T {
+        match hand {
+            Hand::Left => &self.left,
+            Hand::Right => &self.right,
+        }
+    }
+    pub fn get_mut(&mut self, hand: Hand) -> &mut T {
+        match hand {
+            Hand::Left => &mut self.left,
+            Hand::Right => &mut self.right,
+        }
+    }
+}
+
+/// Name of the action set used by [`XrControllers`]
+pub const CONTROLLER_ACTION_SET: &str = "controller_input";
+
+pub fn setup_xr_controllers(
+    mut commands: Commands,
+    instance: Res<XrInstance>,
+    mut action_sets: ResMut<SetupActionSets>,
+) {
+    let palm_pose = instance.exts().ext_palm_pose.is_some();
+    commands.insert_resource(XrControllers::new(&mut action_sets, palm_pose));
+}
+
+pub fn post_action_setup_xr_controllers(
+    action_sets: Res<XrActionSets>,
+    mut controllers: ResMut<XrControllers>,
+    session: Res<XrSession>,
+) {
+    let s = Session::<AnyGraphics>::clone(&session);
+    let grip_action = action_sets
+        .get_action_posef(CONTROLLER_ACTION_SET, "grip_pose")
+        .unwrap();
+    let aim_action = action_sets
+        .get_action_posef(CONTROLLER_ACTION_SET, "aim_pose")
+        .unwrap();
+    controllers.grip_space = Some(Handed {
+        left: grip_action
+            .create_space(s.clone(), subaction_path(Hand::Left), Posef::IDENTITY)
+            .unwrap(),
+        right: grip_action
+            .create_space(s.clone(), subaction_path(Hand::Right), Posef::IDENTITY)
+            .unwrap(),
+    });
+    controllers.aim_space = Some(Handed {
+        left: aim_action
+            .create_space(s.clone(), subaction_path(Hand::Left), Posef::IDENTITY)
+            .unwrap(),
+        right: aim_action
+            .create_space(s.clone(), subaction_path(Hand::Right), Posef::IDENTITY)
+            .unwrap(),
+    });
+    if let Ok(palm_action) = action_sets.get_action_posef(CONTROLLER_ACTION_SET, "palm_pose") {
+        controllers.palm_space = Some(Handed {
+            left: palm_action
+                .create_space(s.clone(), subaction_path(Hand::Left), Posef::IDENTITY)
+                .unwrap(),
+            right: palm_action
+                .create_space(s.clone(), subaction_path(Hand::Right), Posef::IDENTITY)
+                .unwrap(),
+        });
+    }
+}
+
+pub fn cleanup_xr_controllers(mut commands: Commands) {
+    commands.remove_resource::<XrControllers>();
+}
+
+/// Adds an [`XrController`] and an [`XrVelocity`] to every controller entity that doesn't have an
+/// [`XrController`] yet
+#[allow(clippy::type_complexity)]
+pub fn insert_xr_controller_components(
+    mut commands: Commands,
+    controller_query: Query<
+        Entity,
+        (
+            Or<(With<OpenXRLeftController>, With<OpenXRRightController>)>,
+            Without<XrController>,
+        ),
+    >,
+) {
+    for entity in &controller_query {
+        commands
+            .entity(entity)
+            .insert((XrController::default(), XrVelocity::default()));
+    }
+}
+
+pub fn update_xr_controller_components(
+    controllers: Res<XrControllers>,
+    mut controller_query: Query<(
+        &mut XrController,
+        Has<OpenXRLeftController>,
+        Has<OpenXRRightController>,
+    )>,
+    frame_state: Res<XrFrameState>,
+    xr_input: Res<XrInput>,
+    session: Res<XrSession>,
+    action_sets: Res<XrActionSets>,
+) {
+    let controllers = controllers.get_ref(&session, &frame_state, &xr_input, &action_sets);
+    for (mut controller, left, right) in &mut controller_query {
+        let hand = match (left, right) {
+            (true, false) => Hand::Left,
+            (false, true) => Hand::Right,
+            _ => continue,
+        };
+        *controller = XrController::from_ref(&controllers, hand);
+    }
+}
+
+/// Profile agnostic controller input, the actions are bound to every supported interaction profile.
+///
+/// The primary button is X/A on Touch and A on Index, the secondary button is Y/B on Touch and B on
+/// Index, Vive wands and WMR controllers report the trackpad as the thumbstick.
+#[derive(Resource)]
+pub struct XrControllers {
+    pub grip_space: Option<Handed<Space>>,
+    pub aim_space: Option<Handed<Space>>,
+    /// only available with `XR_EXT_palm_pose`
+    pub palm_space: Option<Handed<Space>>,
+    /// input of hands that are used as controllers, combined with the actions of the hand
+    pub hand_input: Handed<Option<HandControllerInput>>,
+}
+
+/// Controller input from a tracked hand, written by the `HandAimPlugin`
+#[derive(Clone, Copy, Debug, Default)]
+pub struct HandControllerInput {
+    pub trigger: f32,
+    pub trigger_touched: bool,
+    pub menu_button: bool,
+}
+
+/// Per controller entity copy of the [`XrControllers`] state, inserted on and updated every
+/// frame for entities with `OpenXRLeftController` or `OpenXRRightController`
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct XrController {
+    pub trigger: f32,
+    pub trigger_touched: bool,
+    pub squeeze: f32,
+    pub primary_button: bool,
+    pub primary_button_touched: bool,
+    pub secondary_button: bool,
+    pub secondary_button_touched: bool,
+    pub menu_button: bool,
+    pub thumbstick: Thumbstick,
+    pub thumbstick_touch: bool,
+}
+
+impl XrController {
+    pub fn from_ref(controllers: &XrControllersRef<'_>, hand: Hand) -> Self {
+        Self {
+            trigger: controllers.trigger(hand),
+            trigger_touched: controllers.trigger_touched(hand),
+            squeeze: controllers.squeeze(hand),
+            primary_button: controllers.primary_button(hand),
+            primary_button_touched: controllers.primary_button_touched(hand),
+            secondary_button: controllers.secondary_button(hand),
+            secondary_button_touched: controllers.secondary_button_touched(hand),
+            menu_button: controllers.menu_button(hand),
+            thumbstick: controllers.thumbstick(hand),
+            thumbstick_touch: controllers.thumbstick_touch(hand),
+        }
+    }
+}
+
+pub struct XrControllersRef<'a> {
+    controllers: &'a XrControllers,
+    action_sets: &'a XrActionSets,
+    session: &'a Session<AnyGraphics>,
+    frame_state: &'a FrameState,
+    xr_input: &'a XrInput,
+}
+
+impl XrControllers {
+    pub fn get_ref<'a>(
+        &'a self,
+        session: &'a Session<AnyGraphics>,
+        frame_state: &'a FrameState,
+        xr_input: &'a XrInput,
+        action_sets: &'a XrActionSets,
+    ) -> XrControllersRef<'a> {
+        XrControllersRef {
+            controllers: self,
+            session,
+            frame_state,
+            xr_input,
+            action_sets,
+        }
+    }
+}
+
+impl XrControllersRef<'_> {
+    fn relate(&self, spaces: &Option<Handed<Space>>, hand: Hand) -> (SpaceLocation, SpaceVelocity) {
+        let spaces = match spaces {
+            Some(s) => s,
+            None => return default(),
+        };
+        let space = match hand {
+            Hand::Left => &spaces.left,
+            Hand::Right => &spaces.right,
+        };
+        match space.relate(
+            &self.xr_input.stage,
+            self.frame_state.predicted_display_time,
+        ) {
+            Ok(d) => d,
+            Err(_) => (SpaceLocation::default(), SpaceVelocity::default()),
+        }
+    }
+    fn bool_state(&self, action: &'static str, hand: Hand) -> bool {
+        match self
+            .action_sets
+            .get_action_bool(CONTROLLER_ACTION_SET, action)
+            .map(|a| a.state(self.session, subaction_path(hand)))
+        {
+            Ok(Ok(v)) => v.current_state,
+            _ => default(),
+        }
+    }
+    fn f32_state(&self, action: &'static str, hand: Hand) -> f32 {
+        match self
+            .action_sets
+            .get_action_f32(CONTROLLER_ACTION_SET, action)
+            .map(|a| a.state(self.session, subaction_path(hand)))
+        {
+            Ok(Ok(v)) => v.current_state,
+            _ => default(),
+        }
+    }
+    pub fn grip_space(&self, hand: Hand) -> (SpaceLocation, SpaceVelocity) {
+        self.relate(&self.controllers.grip_space, hand)
+    }
+    pub fn aim_space(&self, hand: Hand) -> (SpaceLocation, SpaceVelocity) {
+        self.relate(&self.controllers.aim_space, hand)
+    }
+    /// invalid when `XR_EXT_palm_pose` isn't enabled
+    pub fn palm_space(&self, hand: Hand) -> (SpaceLocation, SpaceVelocity) {
+        self.relate(&self.controllers.palm_space, hand)
+    }
+    fn hand_input(&self, hand: Hand) -> HandControllerInput {
+        self.controllers.hand_input.get(hand).unwrap_or_default()
+    }
+    pub fn trigger(&self, hand: Hand) -> f32 {
+        self.f32_state("trigger", hand)
+            .max(self.hand_input(hand).trigger)
+    }
+    pub fn trigger_touched(&self, hand: Hand) -> bool {
+        self.bool_state("trigger_touched", hand) || self.hand_input(hand).trigger_touched
+    }
+    pub fn squeeze(&self, hand: Hand) -> f32 {
+        self.f32_state("squeeze", hand)
+    }
+    pub fn primary_button(&self, hand: Hand) -> bool {
+        self.bool_state("primary_button", hand)
+    }
+    pub fn primary_button_touched(&self, hand: Hand) -> bool {
+        self.bool_state("primary_button_touch", hand)
+    }
+    pub fn secondary_button(&self, hand: Hand) -> bool {
+        self.bool_state("secondary_button", hand)
+    }
+    pub fn secondary_button_touched(&self, hand: Hand) -> bool {
+        self.bool_state("secondary_button_touch", hand)
+    }
+    pub fn menu_button(&self, hand: Hand) -> bool {
+        self.bool_state("menu_button", hand) || self.hand_input(hand).menu_button
+    }
+    pub fn thumbstick_touch(&self, hand: Hand) -> bool {
+        self.bool_state("thumbstick_touch", hand)
+    }
+    pub fn thumbstick(&self, hand: Hand) -> Thumbstick {
+        Thumbstick {
+            x: self.f32_state("thumbstick_x", hand),
+            y: self.f32_state("thumbstick_y", hand),
+            click: self.bool_state("thumbstick_click", hand),
+        }
+    }
+}
+
+impl XrControllers {
+    /// `palm_pose` adds the palm pose action, requires `XR_EXT_palm_pose` to be enabled
+    pub fn new(action_sets: &mut SetupActionSets, palm_pose: bool) -> Self {
+        let action_set =
+            action_sets.add_action_set(CONTROLLER_ACTION_SET, "Controller Input".into(), 0);
+        for (name, pretty_name, action_type) in [
+            ("grip_pose", "Grip Pose", ActionType::PoseF),
+            ("aim_pose", "Aim Pose", ActionType::PoseF),
+            ("trigger", "Trigger Pull", ActionType::F32),
+            ("trigger_touched", "Trigger Touch", ActionType::Bool),
+            ("squeeze", "Grip Pull", ActionType::F32),
+            ("primary_button", "Primary Button", ActionType::Bool),
+            (
+                "primary_button_touch",
+                "Primary Button Touch",
+                ActionType::Bool,
+            ),
+            ("secondary_button", "Secondary Button", ActionType::Bool),
+            (
+                "secondary_button_touch",
+                "Secondary Button Touch",
+                ActionType::Bool,
+            ),
+            ("menu_button", "Menu Button", ActionType::Bool),
+            ("thumbstick_x", "Thumbstick X", ActionType::F32),
+            ("thumbstick_y", "Thumbstick Y", ActionType::F32),
+            ("thumbstick_touch", "Thumbstick Touch", ActionType::Bool),
+            ("thumbstick_click", "Thumbstick Click", ActionType::Bool),
+            ("haptic_feedback", "Haptic Feedback", ActionType::Haptic),
+        ] {
+            action_set.new_action(
+                name,
+                pretty_name.into(),
+                action_type,
+                ActionHandednes::Double,
+            );
+        }
+
+        action_set.suggest_binding(
+            "/interaction_profiles/khr/simple_controller",
+            &[
+                XrBinding::new("grip_pose", "/user/hand/left/input/grip/pose"),
+                XrBinding::new("grip_pose", "/user/hand/right/input/grip/pose"),
+                XrBinding::new("aim_pose", "/user/hand/left/input/aim/pose"),
+                XrBinding::new("aim_pose", "/user/hand/right/input/aim/pose"),
+                XrBinding::new("trigger", "/user/hand/left/input/select/click"),
+                XrBinding::new("trigger", "/user/hand/right/input/select/click"),
+                XrBinding::new("menu_button", "/user/hand/left/input/menu/click"),
+                XrBinding::new("menu_button", "/user/hand/right/input/menu/click"),
+                XrBinding::new("haptic_feedback", "/user/hand/left/output/haptic"),
+                XrBinding::new("haptic_feedback", "/user/hand/right/output/haptic"),
+            ],
+        );
+        action_set.suggest_binding(
+            "/interaction_profiles/oculus/touch_controller",
+            &[
+                XrBinding::new("grip_pose", "/user/hand/left/input/grip/pose"),
+                XrBinding::new("grip_pose", "/user/hand/right/input/grip/pose"),
+                XrBinding::new("aim_pose", "/user/hand/left/input/aim/pose"),
+                XrBinding::new("aim_pose", "/user/hand/right/input/aim/pose"),
+                XrBinding::new("trigger", "/user/hand/left/input/trigger/value"),
+                XrBinding::new("trigger", "/user/hand/right/input/trigger/value"),
+                XrBinding::new("trigger_touched", "/user/hand/left/input/trigger/touch"),
+                XrBinding::new("trigger_touched", "/user/hand/right/input/trigger/touch"),
+                XrBinding::new("squeeze", "/user/hand/left/input/squeeze/value"),
+                XrBinding::new("squeeze", "/user/hand/right/input/squeeze/value"),
+                XrBinding::new("primary_button", "/user/hand/left/input/x/click"),
+                XrBinding::new("primary_button", "/user/hand/right/input/a/click"),
+                XrBinding::new("primary_button_touch", "/user/hand/left/input/x/touch"),
+                XrBinding::new("primary_button_touch", "/user/hand/right/input/a/touch"),
+                XrBinding::new("secondary_button", "/user/hand/left/input/y/click"),
+                XrBinding::new("secondary_button", "/user/hand/right/input/b/click"),
+                XrBinding::new("secondary_button_touch", "/user/hand/left/input/y/touch"),
+                XrBinding::new("secondary_button_touch", "/user/hand/right/input/b/touch"),
+                XrBinding::new("menu_button", "/user/hand/left/input/menu/click"),
+                XrBinding::new("thumbstick_x", "/user/hand/left/input/thumbstick/x"),
+                XrBinding::new("thumbstick_x", "/user/hand/right/input/thumbstick/x"),
+                XrBinding::new("thumbstick_y", "/user/hand/left/input/thumbstick/y"),
+                XrBinding::new("thumbstick_y", "/user/hand/right/input/thumbstick/y"),
+                XrBinding::new("thumbstick_touch", "/user/hand/left/input/thumbstick/touch"),
+                XrBinding::new(
+                    "thumbstick_touch",
+                    "/user/hand/right/input/thumbstick/touch",
+                ),
+                XrBinding::new("thumbstick_click", "/user/hand/left/input/thumbstick/click"),
+                XrBinding::new(
+                    "thumbstick_click",
+                    "/user/hand/right/input/thumbstick/click",
+                ),
+                XrBinding::new("haptic_feedback", "/user/hand/left/output/haptic"),
+                XrBinding::new("haptic_feedback", "/user/hand/right/output/haptic"),
+            ],
+        );
+        action_set.suggest_binding(
+            "/interaction_profiles/valve/index_controller",
+            &[
+                XrBinding::new("grip_pose", "/user/hand/left/input/grip/pose"),
+                XrBinding::new("grip_pose", "/user/hand/right/input/grip/pose"),
+                XrBinding::new("aim_pose", "/user/hand/left/input/aim/pose"),
+                XrBinding::new("aim_pose", "/user/hand/right/input/aim/pose"),
+                XrBinding::new("trigger", "/user/hand/left/input/trigger/value"),
+                XrBinding::new("trigger", "/user/hand/right/input/trigger/value"),
+                XrBinding::new("trigger_touched", "/user/hand/left/input/trigger/touch"),
+                XrBinding::new("trigger_touched", "/user/hand/right/input/trigger/touch"),
+                XrBinding::new("squeeze", "/user/hand/left/input/squeeze/value"),
+                XrBinding::new("squeeze", "/user/hand/right/input/squeeze/value"),
+                XrBinding::new("primary_button", "/user/hand/left/input/a/click"),
+                XrBinding::new("primary_button", "/user/hand/right/input/a/click"),
+                XrBinding::new("primary_button_touch", "/user/hand/left/input/a/touch"),
+                XrBinding::new("primary_button_touch", "/user/hand/right/input/a/touch"),
+                XrBinding::new("secondary_button", "/user/hand/left/input/b/click"),
+                XrBinding::new("secondary_button", "/user/hand/right/input/b/click"),
+                XrBinding::new("secondary_button_touch", "/user/hand/left/input/b/touch"),
+                XrBinding::new("secondary_button_touch", "/user/hand/right/input/b/touch"),
+                XrBinding::new("menu_button", "/user/hand/left/input/system/click"),
+                XrBinding::new("menu_button", "/user/hand/right/input/system/click"),
+                XrBinding::new("thumbstick_x", "/user/hand/left/input/thumbstick/x"),
+                XrBinding::new("thumbstick_x", "/user/hand/right/input/thumbstick/x"),
+                XrBinding::new("thumbstick_y", "/user/hand/left/input/thumbstick/y"),
+                XrBinding::new("thumbstick_y", "/user/hand/right/input/thumbstick/y"),
+                XrBinding::new("thumbstick_touch", "/user/hand/left/input/thumbstick/touch"),
+                XrBinding::new(
+                    "thumbstick_touch",
+                    "/user/hand/right/input/thumbstick/touch",
+                ),
+                XrBinding::new("thumbstick_click", "/user/hand/left/input/thumbstick/click"),
+                XrBinding::new(
+                    "thumbstick_click",
+                    "/user/hand/right/input/thumbstick/click",
+                ),
+                XrBinding::new("haptic_feedback", "/user/hand/left/output/haptic"),
+                XrBinding::new("haptic_feedback", "/user/hand/right/output/haptic"),
+            ],
+        );
+        action_set.suggest_binding(
+            "/interaction_profiles/htc/vive_controller",
+            &[
+                XrBinding::new("grip_pose", "/user/hand/left/input/grip/pose"),
+                XrBinding::new("grip_pose", "/user/hand/right/input/grip/pose"),
+                XrBinding::new("aim_pose", "/user/hand/left/input/aim/pose"),
+                XrBinding::new("aim_pose", "/user/hand/right/input/aim/pose"),
+                XrBinding::new("trigger", "/user/hand/left/input/trigger/value"),
+                XrBinding::new("trigger", "/user/hand/right/input/trigger/value"),
+                XrBinding::new("squeeze", "/user/hand/left/input/squeeze/click"),
+                XrBinding::new("squeeze", "/user/hand/right/input/squeeze/click"),
+                XrBinding::new("menu_button", "/user/hand/left/input/menu/click"),
+                XrBinding::new("menu_button", "/user/hand/right/input/menu/click"),
+                XrBinding::new("thumbstick_x", "/user/hand/left/input/trackpad/x"),
+                XrBinding::new("thumbstick_x", "/user/hand/right/input/trackpad/x"),
+                XrBinding::new("thumbstick_y", "/user/hand/left/input/trackpad/y"),
+                XrBinding::new("thumbstick_y", "/user/hand/right/input/trackpad/y"),
+                XrBinding::new("thumbstick_touch", "/user/hand/left/input/trackpad/touch"),
+                XrBinding::new("thumbstick_touch", "/user/hand/right/input/trackpad/touch"),
+                XrBinding::new("thumbstick_click", "/user/hand/left/input/trackpad/click"),
+                XrBinding::new("thumbstick_click", "/user/hand/right/input/trackpad/click"),
+                XrBinding::new("haptic_feedback", "/user/hand/left/output/haptic"),
+                XrBinding::new("haptic_feedback", "/user/hand/right/output/haptic"),
+            ],
+        );
+        action_set.suggest_binding(
+            "/interaction_profiles/microsoft/motion_controller",
+            &[
+                XrBinding::new("grip_pose", "/user/hand/left/input/grip/pose"),
+                XrBinding::new("grip_pose", "/user/hand/right/input/grip/pose"),
+                XrBinding::new("aim_pose", "/user/hand/left/input/aim/pose"),
+                XrBinding::new("aim_pose", "/user/hand/right/input/aim/pose"),
+                XrBinding::new("trigger", "/user/hand/left/input/trigger/value"),
+                XrBinding::new("trigger", "/user/hand/right/input/trigger/value"),
+                XrBinding::new("squeeze", "/user/hand/left/input/squeeze/click"),
+                XrBinding::new("squeeze", "/user/hand/right/input/squeeze/click"),
+                XrBinding::new("menu_button", "/user/hand/left/input/menu/click"),
+                XrBinding::new("menu_button", "/user/hand/right/input/menu/click"),
+                XrBinding::new("thumbstick_x", "/user/hand/left/input/thumbstick/x"),
+                XrBinding::new("thumbstick_x", "/user/hand/right/input/thumbstick/x"),
+                XrBinding::new("thumbstick_y", "/user/hand/left/input/thumbstick/y"),
+                XrBinding::new("thumbstick_y", "/user/hand/right/input/thumbstick/y"),
+                XrBinding::new("thumbstick_click", "/user/hand/left/input/thumbstick/click"),
+                XrBinding::new(
+                    "thumbstick_click",
+                    "/user/hand/right/input/thumbstick/click",
+                ),
+                XrBinding::new("haptic_feedback", "/user/hand/left/output/haptic"),
+                XrBinding::new("haptic_feedback", "/user/hand/right/output/haptic"),
+            ],
+        );
+
+        if palm_pose {
+            action_set.new_action(
+                "palm_pose",
+                "Palm Pose".into(),
+                ActionType::PoseF,
+                ActionHandednes::Double,
+            );
+            for profile in [
+                "/interaction_profiles/khr/simple_controller",
+                "/interaction_profiles/oculus/touch_controller",
+                "/interaction_profiles/valve/index_controller",
+                "/interaction_profiles/htc/vive_controller",
+                "/interaction_profiles/microsoft/motion_controller",
+            ] {
+                action_set.suggest_binding(
+                    profile,
+                    &[
+                        XrBinding::new("palm_pose", "/user/hand/left/input/palm_ext/pose"),
+                        XrBinding::new("palm_pose", "/user/hand/right/input/palm_ext/pose"),
+                    ],
+                );
+            }
+        }
+
+        XrControllers {
+            grip_space: None,
+            aim_space: None,
+            palm_space: None,
+            hand_input: default(),
+        }
+    }
+}
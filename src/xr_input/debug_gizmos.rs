@@ -13,7 +13,7 @@ use crate::{
 };
 
 use crate::xr_input::{
-    oculus_touch::{OculusController, OculusControllerRef},
+    controllers::{XrControllers, XrControllersRef},
     Hand,
 };
 
@@ -35,7 +35,7 @@ impl Plugin for OpenXrDebugRenderer {
 #[allow(clippy::too_many_arguments, clippy::complexity)]
 pub fn draw_gizmos(
     mut gizmos: Gizmos,
-    oculus_controller: Res<OculusController>,
+    controllers: Res<XrControllers>,
     frame_state: Res<XrFrameState>,
     xr_input: Res<XrInput>,
     session: Res<XrSession>,
@@ -94,7 +94,7 @@ pub fn draw_gizmos(
     //lock frame
     // let frame_state = *frame_state.lock().unwrap();
     //get controller
-    let controller = oculus_controller.get_ref(&session, &frame_state, &xr_input, &action_sets);
+    let controller = controllers.get_ref(&session, &frame_state, &xr_input, &action_sets);
     let root = tracking_root_query.get_single();
     match root {
         Ok(position) => {
@@ -133,7 +133,7 @@ pub fn draw_gizmos(
 
 fn draw_hand_gizmo(
     gizmos: &mut Gizmos,
-    controller: &OculusControllerRef<'_>,
+    controller: &XrControllersRef<'_>,
     hand: Hand,
     hand_transform: &GlobalTransform,
 ) {
@@ -176,10 +176,10 @@ fn draw_hand_gizmo(
 
             //button b
             let mut b_color = off_color;
-            if controller.y_button_touched() {
+            if controller.secondary_button_touched(Hand::Left) {
                 b_color = touch_color;
             }
-            if controller.y_button() {
+            if controller.secondary_button(Hand::Left) {
                 b_color = pressed_color;
             }
 
@@ -195,10 +195,10 @@ fn draw_hand_gizmo(
 
             //button a
             let mut a_color = off_color;
-            if controller.x_button_touched() {
+            if controller.primary_button_touched(Hand::Left) {
                 a_color = touch_color;
             }
-            if controller.x_button() {
+            if controller.primary_button(Hand::Left) {
                 a_color = pressed_color;
             }
 
@@ -305,10 +305,10 @@ fn draw_hand_gizmo(
 
             //button b
             let mut b_color = off_color;
-            if controller.b_button_touched() {
+            if controller.secondary_button_touched(Hand::Right) {
                 b_color = touch_color;
             }
-            if controller.b_button() {
+            if controller.secondary_button(Hand::Right) {
                 b_color = pressed_color;
             }
 
@@ -324,10 +324,10 @@ fn draw_hand_gizmo(
 
             //button a
             let mut a_color = off_color;
-            if controller.a_button_touched() {
+            if controller.primary_button_touched(Hand::Right) {
                 a_color = touch_color;
             }
-            if controller.a_button() {
+            if controller.primary_button(Hand::Right) {
                 a_color = pressed_color;
             }
 
@@ -0,0 +1,193 @@
+use std::ptr;
+
+use bevy::prelude::*;
+use openxr as xr;
+
+use crate::{
+    cvt,
+    input::XrInput,
+    resources::{XrFrameState, XrInstance, XrSession},
+    xr_init::{xr_only, XrCleanup, XrPostSetup, XrPreSetup, XrSetup},
+};
+
+use super::{
+    actions::{ActionHandednes, ActionType, SetupActionSets, XrActionSets, XrBinding},
+    interactions::{XRInteractorState, XRRayInteractor},
+    trackers::{
+        verify_quat, AimPose, OpenXRTracker, TrackingStatusParams, XrTrackingStatus, XrVelocity,
+    },
+    QuatConv, Vec3Conv,
+};
+
+const EYE_GAZE_ACTION_SET: &str = "eye_gaze";
+const EYE_GAZE_ACTION: &str = "gaze_pose";
+
+/// Tracks where the user is looking through `XR_EXT_eye_gaze_interaction`, the extension has to
+/// be enabled with `XrExtensions::enable_eye_gaze_interaction`
+pub struct XrEyeGazePlugin;
+
+impl Plugin for XrEyeGazePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(XrPreSetup, check_for_eye_gaze);
+        app.add_systems(
+            XrSetup,
+            setup_eye_gaze_action_set.run_if(resource_exists::<XrEyeGazeSupported>),
+        );
+        app.add_systems(
+            XrPostSetup,
+            spawn_eye_gaze.run_if(resource_exists::<XrEyeGazeSupported>),
+        );
+        app.add_systems(
+            Update,
+            update_eye_gaze
+                .run_if(resource_exists::<XrEyeGazeSpace>)
+                .run_if(xr_only()),
+        );
+        app.add_systems(XrCleanup, cleanup_eye_gaze);
+    }
+}
+
+/// Inserted when the runtime supports eye gaze interaction
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct XrEyeGazeSupported;
+
+#[derive(Resource)]
+pub struct XrEyeGazeSpace(pub xr::Space);
+
+/// The gaze ray, the entity looks down its forward direction. It is also a
+/// [`XRRayInteractor`] so gazing at an `XRInteractable` hovers it, setting the
+/// [`XRInteractorState`] to selecting selects it
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct XrEyeGaze;
+
+#[inline]
+pub fn supports_eye_gaze_interaction(
+    instance: &XrInstance,
+    system: xr::SystemId,
+) -> xr::Result<bool> {
+    unsafe {
+        let mut eye_gaze = xr::sys::SystemEyeGazeInteractionPropertiesEXT {
+            ty: xr::sys::SystemEyeGazeInteractionPropertiesEXT::TYPE,
+            next: ptr::null_mut(),
+            supports_eye_gaze_interaction: xr::sys::FALSE,
+        };
+        let mut p = xr::sys::SystemProperties::out(&mut eye_gaze as *mut _ as _);
+        cvt((instance.fp().get_system_properties)(
+            instance.as_raw(),
+            system,
+            p.as_mut_ptr(),
+        ))?;
+        Ok(eye_gaze.supports_eye_gaze_interaction.into())
+    }
+}
+
+fn check_for_eye_gaze(mut commands: Commands, instance: Res<XrInstance>) {
+    let supported = instance.exts().ext_eye_gaze_interaction.is_some()
+        && instance
+            .system(xr::FormFactor::HEAD_MOUNTED_DISPLAY)
+            .and_then(|system| supports_eye_gaze_interaction(&instance, system))
+            .is_ok_and(|v| v);
+    if supported {
+        info!("eye gaze interaction!");
+        commands.insert_resource(XrEyeGazeSupported);
+    }
+}
+
+fn setup_eye_gaze_action_set(mut action_sets: ResMut<SetupActionSets>) {
+    let action_set = action_sets.add_action_set(EYE_GAZE_ACTION_SET, "Eye Gaze".into(), 0);
+    action_set.new_action(
+        EYE_GAZE_ACTION,
+        "Gaze Pose".into(),
+        ActionType::PoseF,
+        ActionHandednes::Single,
+    );
+    action_set.suggest_binding(
+        "/interaction_profiles/ext/eye_gaze_interaction",
+        &[XrBinding::new(
+            EYE_GAZE_ACTION,
+            "/user/eyes_ext/input/gaze_ext/pose",
+        )],
+    );
+}
+
+fn spawn_eye_gaze(mut commands: Commands, action_sets: Res<XrActionSets>, session: Res<XrSession>) {
+    let action = match action_sets.get_action_posef(EYE_GAZE_ACTION_SET, EYE_GAZE_ACTION) {
+        Ok(a) => a,
+        Err(err) => {
+            warn!("unable to get eye gaze action: {}", err);
+            return;
+        }
+    };
+    let space = match action.create_space(
+        xr::Session::<xr::AnyGraphics>::clone(&session),
+        xr::Path::NULL,
+        xr::Posef::IDENTITY,
+    ) {
+        Ok(s) => s,
+        Err(err) => {
+            warn!("unable to create eye gaze space: {}", err);
+            return;
+        }
+    };
+    commands.insert_resource(XrEyeGazeSpace(space));
+    commands.spawn((
+        SpatialBundle::default(),
+        Name::new("OpenXR Eye Gaze"),
+        OpenXRTracker,
+        XrEyeGaze,
+        XrVelocity::default(),
+        AimPose(Transform::IDENTITY),
+        XRRayInteractor,
+        XRInteractorState::default(),
+    ));
+}
+
+fn cleanup_eye_gaze(mut commands: Commands, gaze_query: Query<Entity, With<XrEyeGaze>>) {
+    for e in &gaze_query {
+        commands.entity(e).despawn_recursive();
+    }
+    commands.remove_resource::<XrEyeGazeSpace>();
+    commands.remove_resource::<XrEyeGazeSupported>();
+}
+
+#[allow(clippy::type_complexity)]
+fn update_eye_gaze(
+    space: Res<XrEyeGazeSpace>,
+    mut gaze_query: Query<
+        (
+            Entity,
+            &mut Transform,
+            Option<&mut AimPose>,
+            Option<&mut XrVelocity>,
+            Option<&mut XrTrackingStatus>,
+            Option<&mut Visibility>,
+        ),
+        With<XrEyeGaze>,
+    >,
+    frame_state: Res<XrFrameState>,
+    xr_input: Res<XrInput>,
+    mut tracking: TrackingStatusParams,
+) {
+    let (location, velocity) = match space
+        .0
+        .relate(&xr_input.stage, frame_state.predicted_display_time)
+    {
+        Ok(relation) => relation,
+        Err(_) => (xr::SpaceLocation::default(), xr::SpaceVelocity::default()),
+    };
+    for (entity, mut transform, aim_pose, gaze_velocity, status, visibility) in &mut gaze_query {
+        let status = tracking.update(entity, location.location_flags, status, visibility);
+        if let Some(mut gaze_velocity) = gaze_velocity {
+            *gaze_velocity = XrVelocity::from_space_velocity(&velocity);
+        }
+        if status.position_valid {
+            transform.translation = location.pose.position.to_vec3();
+        }
+        if status.orientation_valid {
+            transform.rotation = verify_quat(location.pose.orientation.to_quat());
+        }
+        if let Some(mut aim_pose) = aim_pose {
+            aim_pose.0 = *transform;
+        }
+    }
+}
@@ -0,0 +1,270 @@
+use std::ptr;
+
+use bevy::prelude::*;
+use bevy::render::mesh::morph::{inherit_weights, MorphWeights};
+use openxr as xr;
+use xr::sys::{EyeExpressionHTC, LipExpressionHTC};
+
+use crate::{
+    cvt,
+    resources::{XrFrameState, XrInstance, XrSession},
+    xr_init::{xr_only, XrCleanup, XrSetup},
+};
+
+pub const EYE_EXPRESSION_COUNT: usize = 14;
+pub const LIP_EXPRESSION_COUNT: usize = 37;
+
+/// Eye and lip expression weights through `XR_HTC_facial_tracking`, the extension has to be
+/// enabled with `XrExtensions::enable_facial_tracking`
+pub struct XrFacialTrackingPlugin;
+
+impl Plugin for XrFacialTrackingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(XrSetup, setup_facial_tracking);
+        app.add_systems(
+            PreUpdate,
+            update_facial_tracking
+                .run_if(resource_exists::<XrFacialTracking>)
+                .run_if(xr_only()),
+        );
+        app.add_systems(
+            PostUpdate,
+            apply_facial_expression_morph_weights
+                .before(inherit_weights)
+                .run_if(resource_exists::<XrFacialTracking>)
+                .run_if(xr_only()),
+        );
+        app.add_systems(XrCleanup, cleanup_facial_tracking);
+    }
+}
+
+/// A single expression weight of either tracker
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FacialExpression {
+    Eye(EyeExpressionHTC),
+    Lip(LipExpressionHTC),
+}
+
+/// The latest expression weights from 0.0 to 1.0, indexed by [`EyeExpressionHTC`] and
+/// [`LipExpressionHTC`]. The weights of an inactive tracker are all zero
+#[derive(Resource)]
+pub struct XrFacialTracking {
+    fp: xr::raw::FacialTrackingHTC,
+    eye_tracker: Option<xr::sys::FacialTrackerHTC>,
+    lip_tracker: Option<xr::sys::FacialTrackerHTC>,
+    pub eye_active: bool,
+    pub lip_active: bool,
+    pub eye_weights: [f32; EYE_EXPRESSION_COUNT],
+    pub lip_weights: [f32; LIP_EXPRESSION_COUNT],
+}
+
+impl XrFacialTracking {
+    pub fn has_eye_tracker(&self) -> bool {
+        self.eye_tracker.is_some()
+    }
+    pub fn has_lip_tracker(&self) -> bool {
+        self.lip_tracker.is_some()
+    }
+    pub fn eye(&self, expression: EyeExpressionHTC) -> f32 {
+        self.eye_weights
+            .get(expression.into_raw() as usize)
+            .copied()
+            .unwrap_or_default()
+    }
+    pub fn lip(&self, expression: LipExpressionHTC) -> f32 {
+        self.lip_weights
+            .get(expression.into_raw() as usize)
+            .copied()
+            .unwrap_or_default()
+    }
+    pub fn get(&self, expression: FacialExpression) -> f32 {
+        match expression {
+            FacialExpression::Eye(e) => self.eye(e),
+            FacialExpression::Lip(l) => self.lip(l),
+        }
+    }
+}
+
+/// Maps facial expressions onto the morph targets of a mesh, add it next to the
+/// [`MorphWeights`] of a glTF node
+#[derive(Component, Clone, Debug, Default)]
+pub struct FacialExpressionMorphTargets(pub Vec<(FacialExpression, usize)>);
+
+impl FacialExpressionMorphTargets {
+    /// Looks up the morph target index of every expression by name, `names` usually comes from
+    /// `Mesh::morph_target_names`. Expressions without a matching target are skipped
+    pub fn from_names(names: &[String], mapping: &[(FacialExpression, &str)]) -> Self {
+        Self(
+            mapping
+                .iter()
+                .filter_map(|(expression, name)| {
+                    names
+                        .iter()
+                        .position(|n| n == name)
+                        .map(|index| (*expression, index))
+                })
+                .collect(),
+        )
+    }
+}
+
+/// eye and lip tracking support of the system
+#[inline]
+pub fn supports_facial_tracking(
+    instance: &XrInstance,
+    system: xr::SystemId,
+) -> xr::Result<(bool, bool)> {
+    unsafe {
+        let mut facial = xr::sys::SystemFacialTrackingPropertiesHTC {
+            ty: xr::sys::SystemFacialTrackingPropertiesHTC::TYPE,
+            next: ptr::null_mut(),
+            support_eye_facial_tracking: xr::sys::FALSE,
+            support_lip_facial_tracking: xr::sys::FALSE,
+        };
+        let mut p = xr::sys::SystemProperties::out(&mut facial as *mut _ as _);
+        cvt((instance.fp().get_system_properties)(
+            instance.as_raw(),
+            system,
+            p.as_mut_ptr(),
+        ))?;
+        Ok((
+            facial.support_eye_facial_tracking.into(),
+            facial.support_lip_facial_tracking.into(),
+        ))
+    }
+}
+
+fn create_facial_tracker(
+    fp: &xr::raw::FacialTrackingHTC,
+    session: &XrSession,
+    facial_tracking_type: xr::sys::FacialTrackingTypeHTC,
+) -> xr::Result<xr::sys::FacialTrackerHTC> {
+    let create_info = xr::sys::FacialTrackerCreateInfoHTC {
+        ty: xr::sys::FacialTrackerCreateInfoHTC::TYPE,
+        next: ptr::null(),
+        facial_tracking_type,
+    };
+    let mut tracker = xr::sys::FacialTrackerHTC::NULL;
+    unsafe {
+        cvt((fp.create_facial_tracker)(
+            session.as_raw(),
+            &create_info,
+            &mut tracker,
+        ))?;
+    }
+    Ok(tracker)
+}
+
+/// fills `weights` and returns whether the tracker is active
+fn get_facial_expressions(
+    fp: &xr::raw::FacialTrackingHTC,
+    tracker: xr::sys::FacialTrackerHTC,
+    time: xr::Time,
+    weights: &mut [f32],
+) -> xr::Result<bool> {
+    let mut expressions = xr::sys::FacialExpressionsHTC {
+        ty: xr::sys::FacialExpressionsHTC::TYPE,
+        next: ptr::null(),
+        is_active: xr::sys::FALSE,
+        sample_time: time,
+        expression_count: weights.len() as u32,
+        expression_weightings: weights.as_mut_ptr(),
+    };
+    unsafe {
+        cvt((fp.get_facial_expressions)(tracker, &mut expressions))?;
+    }
+    Ok(expressions.is_active.into())
+}
+
+fn setup_facial_tracking(
+    mut commands: Commands,
+    instance: Res<XrInstance>,
+    session: Res<XrSession>,
+) {
+    let fp = match instance.exts().htc_facial_tracking {
+        Some(fp) => fp,
+        None => return,
+    };
+    let (eye, lip) = match instance
+        .system(xr::FormFactor::HEAD_MOUNTED_DISPLAY)
+        .and_then(|system| supports_facial_tracking(&instance, system))
+    {
+        Ok(support) => support,
+        Err(err) => {
+            warn!("unable to query facial tracking support: {}", err);
+            return;
+        }
+    };
+    let create = |supported: bool, ty: xr::sys::FacialTrackingTypeHTC| {
+        if !supported {
+            return None;
+        }
+        create_facial_tracker(&fp, &session, ty)
+            .map_err(|err| warn!("unable to create {:?} facial tracker: {}", ty, err))
+            .ok()
+    };
+    let eye_tracker = create(eye, xr::sys::FacialTrackingTypeHTC::EYE_DEFAULT);
+    let lip_tracker = create(lip, xr::sys::FacialTrackingTypeHTC::LIP_DEFAULT);
+    if eye_tracker.is_none() && lip_tracker.is_none() {
+        return;
+    }
+    info!("facial tracking!");
+    commands.insert_resource(XrFacialTracking {
+        fp,
+        eye_tracker,
+        lip_tracker,
+        eye_active: false,
+        lip_active: false,
+        eye_weights: [0.0; EYE_EXPRESSION_COUNT],
+        lip_weights: [0.0; LIP_EXPRESSION_COUNT],
+    });
+}
+
+fn cleanup_facial_tracking(mut commands: Commands, tracking: Option<Res<XrFacialTracking>>) {
+    if let Some(tracking) = tracking {
+        for tracker in [tracking.eye_tracker, tracking.lip_tracker]
+            .into_iter()
+            .flatten()
+        {
+            unsafe {
+                (tracking.fp.destroy_facial_tracker)(tracker);
+            }
+        }
+    }
+    commands.remove_resource::<XrFacialTracking>();
+}
+
+fn update_facial_tracking(mut tracking: ResMut<XrFacialTracking>, frame_state: Res<XrFrameState>) {
+    let tracking = &mut *tracking;
+    let time = frame_state.predicted_display_time;
+    if let Some(tracker) = tracking.eye_tracker {
+        tracking.eye_active =
+            get_facial_expressions(&tracking.fp, tracker, time, &mut tracking.eye_weights)
+                .unwrap_or(false);
+        if !tracking.eye_active {
+            tracking.eye_weights = [0.0; EYE_EXPRESSION_COUNT];
+        }
+    }
+    if let Some(tracker) = tracking.lip_tracker {
+        tracking.lip_active =
+            get_facial_expressions(&tracking.fp, tracker, time, &mut tracking.lip_weights)
+                .unwrap_or(false);
+        if !tracking.lip_active {
+            tracking.lip_weights = [0.0; LIP_EXPRESSION_COUNT];
+        }
+    }
+}
+
+fn apply_facial_expression_morph_weights(
+    tracking: Res<XrFacialTracking>,
+    mut morph_query: Query<(&FacialExpressionMorphTargets, &mut MorphWeights)>,
+) {
+    for (targets, mut morph_weights) in &mut morph_query {
+        let weights = morph_weights.weights_mut();
+        for (expression, index) in &targets.0 {
+            if let Some(weight) = weights.get_mut(*index) {
+                *weight = tracking.get(*expression);
+            }
+        }
+    }
+}
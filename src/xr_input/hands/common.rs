@@ -7,7 +7,10 @@ use bevy::{
     transform::components::GlobalTransform,
 };
 
-use crate::xr_input::{trackers::OpenXRTracker, Hand};
+use crate::xr_input::{
+    trackers::{OpenXRTracker, XrVelocity},
+    Hand,
+};
 
 use super::{BoneTrackingStatus, HandBone};
 
@@ -47,6 +50,39 @@ pub struct HandResource {
     pub little: LittleResource,
 }
 
+impl HandResource {
+    pub fn get_bone(&self, bone: HandBone) -> Entity {
+        match bone {
+            HandBone::Palm => self.palm,
+            HandBone::Wrist => self.wrist,
+            HandBone::ThumbMetacarpal => self.thumb.metacarpal,
+            HandBone::ThumbProximal => self.thumb.proximal,
+            HandBone::ThumbDistal => self.thumb.distal,
+            HandBone::ThumbTip => self.thumb.tip,
+            HandBone::IndexMetacarpal => self.index.metacarpal,
+            HandBone::IndexProximal => self.index.proximal,
+            HandBone::IndexIntermediate => self.index.intermediate,
+            HandBone::IndexDistal => self.index.distal,
+            HandBone::IndexTip => self.index.tip,
+            HandBone::MiddleMetacarpal => self.middle.metacarpal,
+            HandBone::MiddleProximal => self.middle.proximal,
+            HandBone::MiddleIntermediate => self.middle.intermediate,
+            HandBone::MiddleDistal => self.middle.distal,
+            HandBone::MiddleTip => self.middle.tip,
+            HandBone::RingMetacarpal => self.ring.metacarpal,
+            HandBone::RingProximal => self.ring.proximal,
+            HandBone::RingIntermediate => self.ring.intermediate,
+            HandBone::RingDistal => self.ring.distal,
+            HandBone::RingTip => self.ring.tip,
+            HandBone::LittleMetacarpal => self.little.metacarpal,
+            HandBone::LittleProximal => self.little.proximal,
+            HandBone::LittleIntermediate => self.little.intermediate,
+            HandBone::LittleDistal => self.little.distal,
+            HandBone::LittleTip => self.little.tip,
+        }
+    }
+}
+
 impl Default for HandResource {
     fn default() -> Self {
         Self {
@@ -172,6 +208,7 @@ pub fn spawn_hand_entities(mut commands: Commands) {
                     *hand,
                     BoneTrackingStatus::Tracked,
                     HandBoneRadius(0.1),
+                    XrVelocity::default(),
                 ))
                 .id();
             let hand_res = match hand {
@@ -0,0 +1,208 @@
+use bevy::prelude::*;
+use openxr::SpaceLocationFlags;
+
+use crate::{
+    input::XrInput,
+    resources::{XrFrameState, XrSession},
+    xr_init::xr_only,
+    xr_input::{
+        actions::{sync_actions, XrActionSets},
+        controllers::XrControllers,
+        interaction_profiles::{hand_user_path, XrInteractionProfiles},
+        Hand,
+    },
+};
+
+use super::{
+    emulated::{update_hand_skeleton_from_emulated, EmulatedHandMappings},
+    hand_tracking::{locate_hands, update_hand_bones, LocatedHands},
+    HandBone,
+};
+
+/// Picks a [`HandDataSource`] for each hand, which decides what writes its hand bones, and
+/// blends the hand bones over [`HandDataSources::blend_duration`] when it changes.
+///
+/// Tracked hands win, then a controller with finger curl inputs emulates the hand, other
+/// controllers get a simulated open hand. Without any of them the hand keeps its last pose
+pub struct HandDataSourcePlugin;
+
+impl Plugin for HandDataSourcePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HandDataSources>();
+        app.add_event::<HandDataSourceChanged>();
+        app.add_systems(
+            PreUpdate,
+            select_hand_data_sources
+                .after(locate_hands)
+                .after(sync_actions)
+                .before(update_hand_bones)
+                .run_if(resource_exists::<XrControllers>)
+                .run_if(xr_only()),
+        );
+        app.add_systems(
+            Update,
+            update_hand_data_sources
+                .after(update_hand_skeleton_from_emulated)
+                .run_if(xr_only()),
+        );
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum HandDataSource {
+    /// joints from hand tracking
+    Tracked,
+    /// joints posed from the grip pose and finger curl inputs of a controller
+    Emulated,
+    /// an open hand at the grip pose of a controller without finger curl inputs
+    Simulated,
+    /// no data, the hand keeps its last pose
+    #[default]
+    None,
+}
+
+#[derive(Event, Clone, Copy, Debug)]
+pub struct HandDataSourceChanged {
+    pub hand: Hand,
+    pub previous: HandDataSource,
+    pub source: HandDataSource,
+}
+
+#[derive(Clone, Copy)]
+struct HandSourceState {
+    source: HandDataSource,
+    /// the pose the blend starts from
+    blend_from: [Transform; 26],
+    /// blend progress from 0.0 to 1.0
+    blend: f32,
+    /// the last written pose
+    last: [Transform; 26],
+}
+
+impl Default for HandSourceState {
+    fn default() -> Self {
+        Self {
+            source: HandDataSource::None,
+            blend_from: [Transform::IDENTITY; 26],
+            blend: 1.0,
+            last: [Transform::IDENTITY; 26],
+        }
+    }
+}
+
+/// The current [`HandDataSource`] of both hands
+#[derive(Resource, Clone)]
+pub struct HandDataSources {
+    /// seconds it takes to blend from one source to the next
+    pub blend_duration: f32,
+    left: HandSourceState,
+    right: HandSourceState,
+}
+
+impl Default for HandDataSources {
+    fn default() -> Self {
+        Self {
+            blend_duration: 0.1,
+            left: default(),
+            right: default(),
+        }
+    }
+}
+
+impl HandDataSources {
+    pub fn get(&self, hand: Hand) -> HandDataSource {
+        self.state(hand).source
+    }
+    /// whether the hand is still blending from its previous source
+    pub fn is_blending(&self, hand: Hand) -> bool {
+        self.state(hand).blend < 1.0
+    }
+    fn state(&self, hand: Hand) -> &HandSourceState {
+        match hand {
+            Hand::Left => &self.left,
+            Hand::Right => &self.right,
+        }
+    }
+    fn state_mut(&mut self, hand: Hand) -> &mut HandSourceState {
+        match hand {
+            Hand::Left => &mut self.left,
+            Hand::Right => &mut self.right,
+        }
+    }
+}
+
+/// Picks the source of each hand before the hand bones are written, [`update_hand_bones`] and
+/// the hand emulation only write the hands whose source they are
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn select_hand_data_sources(
+    mut sources: ResMut<HandDataSources>,
+    mut changed: EventWriter<HandDataSourceChanged>,
+    located: Res<LocatedHands>,
+    profiles: Option<Res<XrInteractionProfiles>>,
+    mappings: Res<EmulatedHandMappings>,
+    controllers: Res<XrControllers>,
+    session: Res<XrSession>,
+    frame_state: Res<XrFrameState>,
+    xr_input: Res<XrInput>,
+    action_sets: Res<XrActionSets>,
+    time: Res<Time>,
+) {
+    let controller = controllers.get_ref(&session, &frame_state, &xr_input, &action_sets);
+    let blend_step = match sources.blend_duration > 0.0 {
+        true => time.delta_seconds() / sources.blend_duration,
+        false => 1.0,
+    };
+    for hand in [Hand::Left, Hand::Right] {
+        let (grip, _) = controller.grip_space(hand);
+        let grip_valid = grip
+            .location_flags
+            .contains(SpaceLocationFlags::POSITION_VALID | SpaceLocationFlags::ORIENTATION_VALID);
+        let emulated = profiles
+            .as_ref()
+            .and_then(|p| p.get(hand_user_path(hand)))
+            .is_some_and(|p| mappings.supports(&p.path));
+        let source = match (located.get(hand).is_some(), grip_valid) {
+            (true, _) => HandDataSource::Tracked,
+            (false, true) if emulated => HandDataSource::Emulated,
+            (false, true) => HandDataSource::Simulated,
+            (false, false) => HandDataSource::None,
+        };
+        let state = sources.state_mut(hand);
+        if source != state.source {
+            changed.send(HandDataSourceChanged {
+                hand,
+                previous: state.source,
+                source,
+            });
+            // there is nothing to blend from while the hand had no data
+            if state.source != HandDataSource::None {
+                state.blend_from = state.last;
+                state.blend = 0.0;
+            }
+            state.source = source;
+        } else {
+            state.blend = (state.blend + blend_step).min(1.0);
+        }
+    }
+}
+
+/// Blends the written hand bones from the pose of the previous source
+pub(crate) fn update_hand_data_sources(
+    mut sources: ResMut<HandDataSources>,
+    mut bones: Query<(&mut Transform, &Hand, &HandBone)>,
+) {
+    for (mut transform, hand, bone) in &mut bones {
+        let state = sources.state_mut(*hand);
+        let i = bone.get_index_from_bone();
+        if state.blend < 1.0 {
+            let from = state.blend_from[i];
+            let t = state.blend * state.blend * (3.0 - 2.0 * state.blend);
+            *transform = Transform {
+                translation: from.translation.lerp(transform.translation, t),
+                rotation: from.rotation.slerp(transform.rotation, t),
+                scale: from.scale.lerp(transform.scale, t),
+            };
+        }
+        state.last[i] = *transform;
+    }
+}
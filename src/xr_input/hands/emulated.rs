@@ -1,23 +1,28 @@
 use std::f32::consts::PI;
 
-use bevy::prelude::*;
-use openxr::{ActionTy, HandJoint};
+use bevy::{prelude::*, utils::HashMap};
+use openxr::{ActionTy, HandJoint, SpaceLocationFlags};
 
 use super::common::{get_bone_gizmo_style, HandBoneRadius};
 use crate::{
-    resources::{XrInstance, XrSession},
+    input::XrInput,
+    resources::{XrFrameState, XrInstance, XrSession},
     xr_init::{xr_only, XrSetup},
     xr_input::{
-        actions::{
-            ActionHandednes, ActionType, SetupActionSet, SetupActionSets, XrActionSets, XrBinding,
-        },
+        actions::{ActionHandednes, ActionType, SetupActionSets, XrActionSets, XrBinding},
+        controllers::XrControllers,
         hand_poses::get_simulated_open_hand_transforms,
-        trackers::{OpenXRLeftController, OpenXRRightController, OpenXRTrackingRoot},
-        Hand,
+        interaction_profiles::{hand_user_path, XrInteractionProfiles},
+        oculus_touch::subaction_path,
+        trackers::{verify_quat, TrackingStatusParams, XrTrackingStatus},
+        Hand, QuatConv, Vec3Conv,
     },
 };
 
-use super::{BoneTrackingStatus, HandBone};
+use super::{
+    data_source::{HandDataSource, HandDataSources},
+    BoneTrackingStatus, Finger, HandBone,
+};
 
 pub enum TouchValue<T: ActionTy> {
     None,
@@ -28,6 +33,7 @@ pub struct HandEmulationPlugin;
 
 impl Plugin for HandEmulationPlugin {
     fn build(&self, app: &mut App) {
+        app.init_resource::<EmulatedHandMappings>();
         app.add_systems(Update, update_hand_skeleton_from_emulated.run_if(xr_only()));
         app.add_systems(XrSetup, setup_hand_emulation_action_set);
     }
@@ -35,210 +41,453 @@ impl Plugin for HandEmulationPlugin {
 
 const HAND_ACTION_SET: &str = "hand_pose_approx";
 
-fn setup_hand_emulation_action_set(mut action_sets: ResMut<SetupActionSets>) {
+/// the finger curl actions indexed by [`Finger`], the thumb action is a bool
+const FINGER_ACTIONS: [&str; 5] = [
+    "thumb_touch",
+    "index_value",
+    "middle_value",
+    "ring_value",
+    "little_value",
+];
+
+/// How the fingers of an emulated hand are read from the inputs of one interaction profile
+#[derive(Clone, Debug, Default)]
+pub struct EmulatedHandMapping {
+    /// binding paths of both hands for each finger, indexed by [`Finger`]. The thumb is bound to
+    /// a bool action, so touch and click inputs fit it best, the other fingers to a float action
+    pub fingers: [Vec<&'static str>; 5],
+    /// curl from 0.0 to 1.0 used for fingers without any binding path, e.g. fingers wrapped
+    /// around a controller without a sensor under them
+    pub default_curl: [f32; 5],
+}
+
+impl EmulatedHandMapping {
+    pub fn with_finger(mut self, finger: Finger, paths: &[&'static str]) -> Self {
+        self.fingers[finger as usize].extend_from_slice(paths);
+        self
+    }
+    pub fn with_default_curl(mut self, finger: Finger, curl: f32) -> Self {
+        self.default_curl[finger as usize] = curl;
+        self
+    }
+    /// the curl of a finger without a binding, `None` if it is read from the controller
+    pub fn unbound_curl(&self, finger: Finger) -> Option<f32> {
+        match self.fingers[finger as usize].is_empty() {
+            true => Some(self.default_curl[finger as usize]),
+            false => None,
+        }
+    }
+    /// binds the middle, ring and little finger to the same grip input
+    fn with_grip_fingers(self, paths: &[&'static str]) -> Self {
+        self.with_finger(Finger::Middle, paths)
+            .with_finger(Finger::Ring, paths)
+            .with_finger(Finger::Little, paths)
+    }
+}
+
+/// The [`EmulatedHandMapping`] of every interaction profile that can drive an emulated hand,
+/// keyed by the interaction profile path. Mappings have to be inserted before [`XrSetup`] runs
+/// for their bindings to be suggested.
+///
+/// Contains mappings for Oculus Touch, Valve Index, HTC Vive, Windows Mixed Reality and the
+/// Khronos simple controller by default. The Index profile doesn't expose its per finger
+/// capacitive sensors, so its grip sensor drives the middle, ring and little finger
+#[derive(Resource, Clone, Debug)]
+pub struct EmulatedHandMappings {
+    mappings: HashMap<&'static str, EmulatedHandMapping>,
+}
+
+impl Default for EmulatedHandMappings {
+    fn default() -> Self {
+        let mut mappings = Self {
+            mappings: HashMap::new(),
+        };
+        mappings.insert(
+            "/interaction_profiles/oculus/touch_controller",
+            oculus_touch_mapping(),
+        );
+        mappings.insert(
+            "/interaction_profiles/valve/index_controller",
+            valve_index_mapping(),
+        );
+        mappings.insert(
+            "/interaction_profiles/htc/vive_controller",
+            htc_vive_mapping(),
+        );
+        mappings.insert(
+            "/interaction_profiles/microsoft/motion_controller",
+            microsoft_motion_mapping(),
+        );
+        mappings.insert(
+            "/interaction_profiles/hp/mixed_reality_controller",
+            hp_mixed_reality_mapping(),
+        );
+        mappings.insert(
+            "/interaction_profiles/khr/simple_controller",
+            khr_simple_mapping(),
+        );
+        mappings
+    }
+}
+
+impl EmulatedHandMappings {
+    /// adds or replaces the mapping of an interaction profile
+    pub fn insert(&mut self, profile_path: &'static str, mapping: EmulatedHandMapping) {
+        self.mappings.insert(profile_path, mapping);
+    }
+    pub fn remove(&mut self, profile_path: &str) -> Option<EmulatedHandMapping> {
+        self.mappings.remove(profile_path)
+    }
+    pub fn get(&self, profile_path: &str) -> Option<&EmulatedHandMapping> {
+        self.mappings.get(profile_path)
+    }
+    /// whether the profile can drive an emulated hand, other controllers only get a simulated
+    /// open hand
+    pub fn supports(&self, profile_path: &str) -> bool {
+        self.mappings.contains_key(profile_path)
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (&&'static str, &EmulatedHandMapping)> {
+        self.mappings.iter()
+    }
+}
+
+fn oculus_touch_mapping() -> EmulatedHandMapping {
+    EmulatedHandMapping::default()
+        .with_finger(
+            Finger::Thumb,
+            &[
+                "/user/hand/left/input/thumbstick/touch",
+                "/user/hand/right/input/thumbstick/touch",
+                "/user/hand/left/input/x/touch",
+                "/user/hand/left/input/y/touch",
+                "/user/hand/right/input/a/touch",
+                "/user/hand/right/input/b/touch",
+                "/user/hand/left/input/thumbrest/touch",
+                "/user/hand/right/input/thumbrest/touch",
+            ],
+        )
+        .with_finger(
+            Finger::Index,
+            &[
+                "/user/hand/left/input/trigger/value",
+                "/user/hand/right/input/trigger/value",
+            ],
+        )
+        .with_grip_fingers(&[
+            "/user/hand/left/input/squeeze/value",
+            "/user/hand/right/input/squeeze/value",
+        ])
+}
+
+fn valve_index_mapping() -> EmulatedHandMapping {
+    EmulatedHandMapping::default()
+        .with_finger(
+            Finger::Thumb,
+            &[
+                "/user/hand/left/input/thumbstick/touch",
+                "/user/hand/right/input/thumbstick/touch",
+                "/user/hand/left/input/trackpad/touch",
+                "/user/hand/right/input/trackpad/touch",
+                "/user/hand/left/input/a/touch",
+                "/user/hand/right/input/a/touch",
+                "/user/hand/left/input/b/touch",
+                "/user/hand/right/input/b/touch",
+            ],
+        )
+        .with_finger(
+            Finger::Index,
+            &[
+                "/user/hand/left/input/trigger/value",
+                "/user/hand/right/input/trigger/value",
+            ],
+        )
+        .with_grip_fingers(&[
+            "/user/hand/left/input/squeeze/value",
+            "/user/hand/right/input/squeeze/value",
+        ])
+}
+
+fn htc_vive_mapping() -> EmulatedHandMapping {
+    EmulatedHandMapping::default()
+        .with_finger(
+            Finger::Thumb,
+            &[
+                "/user/hand/left/input/trackpad/touch",
+                "/user/hand/right/input/trackpad/touch",
+            ],
+        )
+        .with_finger(
+            Finger::Index,
+            &[
+                "/user/hand/left/input/trigger/value",
+                "/user/hand/right/input/trigger/value",
+            ],
+        )
+        .with_grip_fingers(&[
+            "/user/hand/left/input/squeeze/click",
+            "/user/hand/right/input/squeeze/click",
+        ])
+}
+
+fn microsoft_motion_mapping() -> EmulatedHandMapping {
+    EmulatedHandMapping::default()
+        .with_finger(
+            Finger::Thumb,
+            &[
+                "/user/hand/left/input/trackpad/touch",
+                "/user/hand/right/input/trackpad/touch",
+            ],
+        )
+        .with_finger(
+            Finger::Index,
+            &[
+                "/user/hand/left/input/trigger/value",
+                "/user/hand/right/input/trigger/value",
+            ],
+        )
+        .with_grip_fingers(&[
+            "/user/hand/left/input/squeeze/click",
+            "/user/hand/right/input/squeeze/click",
+        ])
+}
+
+/// the HP Reverb G2 controller has no touch sensors, the thumb rests half curled. Only suggested
+/// with `XrExtensions::enable_hp_mixed_reality_controller`
+fn hp_mixed_reality_mapping() -> EmulatedHandMapping {
+    EmulatedHandMapping::default()
+        .with_default_curl(Finger::Thumb, 0.5)
+        .with_finger(
+            Finger::Index,
+            &[
+                "/user/hand/left/input/trigger/value",
+                "/user/hand/right/input/trigger/value",
+            ],
+        )
+        .with_grip_fingers(&[
+            "/user/hand/left/input/squeeze/value",
+            "/user/hand/right/input/squeeze/value",
+        ])
+}
+
+/// only the index finger is read, the other fingers hold the controller
+fn khr_simple_mapping() -> EmulatedHandMapping {
+    EmulatedHandMapping::default()
+        .with_default_curl(Finger::Thumb, 0.5)
+        .with_finger(
+            Finger::Index,
+            &[
+                "/user/hand/left/input/select/click",
+                "/user/hand/right/input/select/click",
+            ],
+        )
+        .with_default_curl(Finger::Middle, 0.8)
+        .with_default_curl(Finger::Ring, 0.8)
+        .with_default_curl(Finger::Little, 0.8)
+}
+
+/// whether the interaction profile can be suggested, some profiles need an extension
+fn profile_available(instance: &XrInstance, profile: &str) -> bool {
+    match profile {
+        "/interaction_profiles/hp/mixed_reality_controller" => {
+            instance.exts().ext_hp_mixed_reality_controller.is_some()
+        }
+        _ => true,
+    }
+}
+
+fn setup_hand_emulation_action_set(
+    instance: Res<XrInstance>,
+    mut action_sets: ResMut<SetupActionSets>,
+    mappings: Res<EmulatedHandMappings>,
+) {
     let action_set =
         action_sets.add_action_set(HAND_ACTION_SET, "Hand Pose Approximaiton".into(), 0);
     action_set.new_action(
-        "thumb_touch",
+        FINGER_ACTIONS[Finger::Thumb as usize],
         "Thumb Touched".into(),
         ActionType::Bool,
         ActionHandednes::Double,
     );
     action_set.new_action(
-        "thumb_x",
-        "Thumb X".into(),
-        ActionType::F32,
-        ActionHandednes::Double,
-    );
-    action_set.new_action(
-        "thumb_y",
-        "Thumb Y".into(),
-        ActionType::F32,
-        ActionHandednes::Double,
-    );
-
-    action_set.new_action(
-        "index_touch",
-        "Index Finger Touched".into(),
-        ActionType::Bool,
-        ActionHandednes::Double,
-    );
-    action_set.new_action(
-        "index_value",
+        FINGER_ACTIONS[Finger::Index as usize],
         "Index Finger Pull".into(),
         ActionType::F32,
         ActionHandednes::Double,
     );
-
     action_set.new_action(
-        "middle_value",
+        FINGER_ACTIONS[Finger::Middle as usize],
         "Middle Finger Pull".into(),
         ActionType::F32,
         ActionHandednes::Double,
     );
     action_set.new_action(
-        "ring_value",
+        FINGER_ACTIONS[Finger::Ring as usize],
         "Ring Finger Pull".into(),
         ActionType::F32,
         ActionHandednes::Double,
     );
     action_set.new_action(
-        "little_value",
+        FINGER_ACTIONS[Finger::Little as usize],
         "Little Finger Pull".into(),
         ActionType::F32,
         ActionHandednes::Double,
     );
 
-    suggest_oculus_touch_profile(action_set);
-}
-
-fn suggest_oculus_touch_profile(action_set: &mut SetupActionSet) {
-    action_set.suggest_binding(
-        "/interaction_profiles/oculus/touch_controller",
-        &[
-            XrBinding::new("thumb_x", "/user/hand/left/input/thumbstick/x"),
-            XrBinding::new("thumb_x", "/user/hand/right/input/thumbstick/x"),
-            XrBinding::new("thumb_y", "/user/hand/left/input/thumbstick/y"),
-            XrBinding::new("thumb_y", "/user/hand/right/input/thumbstick/y"),
-            XrBinding::new("thumb_touch", "/user/hand/left/input/thumbstick/touch"),
-            XrBinding::new("thumb_touch", "/user/hand/right/input/thumbstick/touch"),
-            XrBinding::new("thumb_touch", "/user/hand/left/input/x/touch"),
-            XrBinding::new("thumb_touch", "/user/hand/left/input/y/touch"),
-            XrBinding::new("thumb_touch", "/user/hand/right/input/a/touch"),
-            XrBinding::new("thumb_touch", "/user/hand/right/input/b/touch"),
-            XrBinding::new("thumb_touch", "/user/hand/left/input/thumbrest/touch"),
-            XrBinding::new("thumb_touch", "/user/hand/right/input/thumbrest/touch"),
-            XrBinding::new("index_touch", "/user/hand/left/input/trigger/touch"),
-            XrBinding::new("index_value", "/user/hand/left/input/trigger/value"),
-            XrBinding::new("index_touch", "/user/hand/right/input/trigger/touch"),
-            XrBinding::new("index_value", "/user/hand/right/input/trigger/value"),
-            XrBinding::new("middle_value", "/user/hand/left/input/squeeze/value"),
-            XrBinding::new("middle_value", "/user/hand/right/input/squeeze/value"),
-            XrBinding::new("ring_value", "/user/hand/left/input/squeeze/value"),
-            XrBinding::new("ring_value", "/user/hand/right/input/squeeze/value"),
-            XrBinding::new("little_value", "/user/hand/left/input/squeeze/value"),
-            XrBinding::new("little_value", "/user/hand/right/input/squeeze/value"),
-        ],
-    );
+    for (profile, mapping) in mappings.iter() {
+        if !profile_available(&instance, profile) {
+            debug!(
+                "not suggesting hand emulation bindings for {}, its extension isn't enabled",
+                profile
+            );
+            continue;
+        }
+        let bindings = Finger::get_all_fingers()
+            .into_iter()
+            .flat_map(|finger| {
+                mapping.fingers[finger as usize]
+                    .iter()
+                    .map(move |path| XrBinding::new(FINGER_ACTIONS[finger as usize], *path))
+            })
+            .collect::<Vec<_>>();
+        action_set.suggest_binding(*profile, &bindings);
+    }
 }
 
-#[allow(clippy::type_complexity)]
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
 pub(crate) fn update_hand_skeleton_from_emulated(
     session: Res<XrSession>,
-    instance: Res<XrInstance>,
+    frame_state: Res<XrFrameState>,
+    xr_input: Res<XrInput>,
+    controllers: Res<XrControllers>,
     action_sets: Res<XrActionSets>,
-    left_controller_transform: Query<&Transform, With<OpenXRLeftController>>,
-    right_controller_transform: Query<&Transform, With<OpenXRRightController>>,
-    mut bones: Query<
-        (
-            &mut Transform,
-            &HandBone,
-            &Hand,
-            &BoneTrackingStatus,
-            &mut HandBoneRadius,
-        ),
-        (
-            Without<OpenXRLeftController>,
-            Without<OpenXRRightController>,
-            Without<OpenXRTrackingRoot>,
-        ),
-    >,
+    mappings: Res<EmulatedHandMappings>,
+    profiles: Option<Res<XrInteractionProfiles>>,
+    sources: Option<Res<HandDataSources>>,
+    mut tracking: TrackingStatusParams,
+    mut bones: Query<(
+        Entity,
+        &mut Transform,
+        &HandBone,
+        &Hand,
+        &BoneTrackingStatus,
+        &mut HandBoneRadius,
+        Option<&mut XrTrackingStatus>,
+        Option<&mut Visibility>,
+    )>,
 ) {
-    //get the transforms outside the loop
-    let left = left_controller_transform.get_single();
-    let right = right_controller_transform.get_single();
-    let mut data: [[Transform; 26]; 2] = [[Transform::default(); 26]; 2];
-    for (subaction_path, hand) in [
-        (
-            instance.string_to_path("/user/hand/left").unwrap(),
-            Hand::Left,
-        ),
-        (
-            instance.string_to_path("/user/hand/right").unwrap(),
-            Hand::Right,
-        ),
-    ] {
-        let thumb_curl = match action_sets
-            .get_action_bool(HAND_ACTION_SET, "thumb_touch")
-            .unwrap()
-            .state(&session, subaction_path)
-            .unwrap()
-            .current_state
+    let controller = controllers.get_ref(&session, &frame_state, &xr_input, &action_sets);
+    let mut data: [Option<[Transform; 26]>; 2] = [None; 2];
+    let mut grip_flags = [SpaceLocationFlags::EMPTY; 2];
+    for hand in [Hand::Left, Hand::Right] {
+        let (grip, _) = controller.grip_space(hand);
+        let flags = grip.location_flags;
+        grip_flags[hand as usize] = flags;
+        // without a data source every hand that isn't tracked is emulated
+        let source = sources
+            .as_ref()
+            .map_or(HandDataSource::Emulated, |s| s.get(hand));
+        if !matches!(source, HandDataSource::Emulated | HandDataSource::Simulated) {
+            continue;
+        }
+        if !flags.contains(SpaceLocationFlags::POSITION_VALID)
+            || !flags.contains(SpaceLocationFlags::ORIENTATION_VALID)
         {
-            true => 1.0,
-            false => 0.0,
-        };
-        let index_curl = action_sets
-            .get_action_f32(HAND_ACTION_SET, "index_value")
-            .unwrap()
-            .state(&session, subaction_path)
-            .unwrap()
-            .current_state;
-        let middle_curl = action_sets
-            .get_action_f32(HAND_ACTION_SET, "middle_value")
-            .unwrap()
-            .state(&session, subaction_path)
-            .unwrap()
-            .current_state;
-        let ring_curl = action_sets
-            .get_action_f32(HAND_ACTION_SET, "ring_value")
-            .unwrap()
-            .state(&session, subaction_path)
-            .unwrap()
-            .current_state;
-        let little_curl = action_sets
-            .get_action_f32(HAND_ACTION_SET, "little_value")
-            .unwrap()
-            .state(&session, subaction_path)
-            .unwrap()
-            .current_state;
-        match hand {
-            Hand::Left => match left {
-                Ok(hand_transform) => {
-                    data[0] = update_hand_bones_emulated(
-                        hand_transform,
-                        hand,
-                        thumb_curl,
-                        index_curl,
-                        middle_curl,
-                        ring_curl,
-                        little_curl,
-                    );
-                }
-                Err(_) => debug!("no left controller transform for hand bone emulation"),
-            },
-            Hand::Right => match right {
-                Ok(hand_transform) => {
-                    data[1] = update_hand_bones_emulated(
-                        hand_transform,
-                        hand,
-                        thumb_curl,
-                        index_curl,
-                        middle_curl,
-                        ring_curl,
-                        little_curl,
-                    );
-                }
-                Err(_) => debug!("no right controller transform for hand bone emulation"),
-            },
+            debug!("no valid {:?} grip pose for hand bone emulation", hand);
+            continue;
         }
+        let hand_transform = Transform::from_translation(grip.pose.position.to_vec3())
+            .with_rotation(verify_quat(grip.pose.orientation.to_quat()));
+        data[hand as usize] = Some(match source {
+            HandDataSource::Simulated => simulated_open_hand(&hand_transform, hand),
+            _ => {
+                let mapping = profiles
+                    .as_ref()
+                    .and_then(|p| p.get(hand_user_path(hand)))
+                    .and_then(|p| mappings.get(&p.path));
+                let [thumb_curl, index_curl, middle_curl, ring_curl, little_curl] =
+                    finger_curls(&action_sets, &session, hand, mapping);
+                update_hand_bones_emulated(
+                    &hand_transform,
+                    hand,
+                    thumb_curl,
+                    index_curl,
+                    middle_curl,
+                    ring_curl,
+                    little_curl,
+                )
+            }
+        });
     }
-    for (mut t, bone, hand, status, mut radius) in bones.iter_mut() {
+    for (entity, mut t, bone, hand, status, mut radius, tracking_status, visibility) in
+        bones.iter_mut()
+    {
         match status {
             BoneTrackingStatus::Emulated => {}
             BoneTrackingStatus::Tracked => continue,
         }
+        // emulated bones are only as well tracked as the grip they are placed on
+        tracking.update(
+            entity,
+            grip_flags[*hand as usize],
+            tracking_status,
+            visibility,
+        );
+        // without a valid grip pose the hand keeps its last pose
+        let data = match data[*hand as usize] {
+            Some(data) => data,
+            None => continue,
+        };
         radius.0 = get_bone_gizmo_style(bone).0;
 
-        *t = data[match hand {
-            Hand::Left => 0,
-            Hand::Right => 1,
-        }][bone.get_index_from_bone()];
+        *t = data[bone.get_index_from_bone()];
         // *t = t.with_scale(trt.scale);
         // *t = t.with_rotation(trt.rotation * t.rotation);
         // *t = t.with_translation(trt.transform_point(t.translation));
     }
 }
+/// the curl of each finger from the hand emulation actions, fingers the mapping doesn't bind
+/// use its default curl
+fn finger_curls(
+    action_sets: &XrActionSets,
+    session: &XrSession,
+    hand: Hand,
+    mapping: Option<&EmulatedHandMapping>,
+) -> [f32; 5] {
+    let subaction_path = subaction_path(hand);
+    Finger::get_all_fingers().map(|finger| {
+        if let Some(curl) = mapping.and_then(|m| m.unbound_curl(finger)) {
+            return curl;
+        }
+        let action = FINGER_ACTIONS[finger as usize];
+        match finger {
+            Finger::Thumb => match action_sets
+                .get_action_bool(HAND_ACTION_SET, action)
+                .unwrap()
+                .state(session, subaction_path)
+                .unwrap()
+                .current_state
+            {
+                true => 1.0,
+                false => 0.0,
+            },
+            _ => {
+                action_sets
+                    .get_action_f32(HAND_ACTION_SET, action)
+                    .unwrap()
+                    .state(session, subaction_path)
+                    .unwrap()
+                    .current_state
+            }
+        }
+    })
+}
+
+/// An open hand at the grip pose, for controllers without finger curl inputs. Built from
+/// [`get_simulated_open_hand_transforms`] without reading any actions
+pub fn simulated_open_hand(controller_transform: &Transform, hand: Hand) -> [Transform; 26] {
+    update_hand_bones_emulated(controller_transform, hand, 0.0, 0.0, 0.0, 0.0, 0.0)
+}
+
 pub fn update_hand_bones_emulated(
     controller_transform: &Transform,
     hand: Hand,
@@ -525,9 +774,51 @@ pub fn update_hand_bones_emulated(
             }
         }
     }
+    set_emulated_joint_rotations(hand, &mut calc_transforms);
     calc_transforms
 }
 
+/// Rotates the emulated joints from their positions to the OpenXR joint axes, -Z points to the
+/// next joint of the finger and +Y out of the back of the hand
+fn set_emulated_joint_rotations(hand: Hand, transforms: &mut [Transform; 26]) {
+    let position = |bone: HandBone| transforms[bone.get_index_from_bone()].translation;
+    let across = match hand {
+        Hand::Left => position(HandBone::IndexProximal) - position(HandBone::LittleProximal),
+        Hand::Right => position(HandBone::LittleProximal) - position(HandBone::IndexProximal),
+    };
+    let hand_z =
+        (position(HandBone::Wrist) - position(HandBone::MiddleProximal)).normalize_or_zero();
+    let hand_y = hand_z.cross(across).normalize_or_zero();
+    let hand_x = hand_y.cross(hand_z);
+    let mut rotations = [Quat::from_mat3(&Mat3::from_cols(hand_x, hand_y, hand_z)); 26];
+    for finger in Finger::get_all_fingers() {
+        let bones = finger.get_bones();
+        for (i, bone) in bones.iter().enumerate() {
+            // the tip points the same way as the last segment
+            let (from, to) = match bones.get(i + 1) {
+                Some(next) => (*bone, *next),
+                None => (bones[i - 1], *bone),
+            };
+            let z = (position(from) - position(to)).normalize_or_zero();
+            // the thumb bends around the hand Y axis, the fingers around the hand X axis
+            let (x, y) = match finger {
+                Finger::Thumb => {
+                    let x = hand_y.cross(z).normalize_or_zero();
+                    (x, z.cross(x))
+                }
+                _ => {
+                    let y = z.cross(hand_x).normalize_or_zero();
+                    (y.cross(z), y)
+                }
+            };
+            rotations[bone.get_index_from_bone()] = Quat::from_mat3(&Mat3::from_cols(x, y, z));
+        }
+    }
+    for (transform, rotation) in transforms.iter_mut().zip(rotations) {
+        transform.rotation = rotation;
+    }
+}
+
 fn get_bone_curl_angle(bone: HandJoint, curl: f32) -> f32 {
     let mul: f32 = match bone {
         HandJoint::INDEX_PROXIMAL => 0.0,
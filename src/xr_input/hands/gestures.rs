@@ -0,0 +1,326 @@
+use std::f32::consts::FRAC_PI_2;
+
+use bevy::prelude::*;
+
+use crate::{
+    xr_init::{xr_only, XrPostSetup},
+    xr_input::{
+        trackers::{update_open_xr_hmd, OpenXRHMD},
+        Hand,
+    },
+};
+
+use super::{
+    common::{HandBoneRadius, HandsResource},
+    data_source::update_hand_data_sources,
+    Finger, HandBone,
+};
+
+/// Recognises hand gestures from the hand bone entities, works for tracked and emulated hands.
+/// The current gestures are kept in the [`HandGestures`] component on the palm entity of each
+/// hand, changes are sent as [`GestureStarted`] and [`GestureEnded`] events
+pub struct HandGesturePlugin;
+
+impl Plugin for HandGesturePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HandGestureConfig>();
+        app.add_event::<GestureStarted>();
+        app.add_event::<GestureEnded>();
+        app.add_systems(XrPostSetup, insert_hand_gestures);
+        app.add_systems(
+            Update,
+            update_hand_gestures
+                .after(update_hand_data_sources)
+                .after(update_open_xr_hmd)
+                .run_if(xr_only()),
+        );
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HandGesture {
+    /// thumb tip touching the tip of the finger, never active for [`Finger::Thumb`]
+    Pinch(Finger),
+    /// all fingers but the thumb curled, not while [`HandGesture::ThumbsUp`] is active
+    Grab,
+    /// index finger extended, the other fingers but the thumb curled
+    Point,
+    /// all fingers extended
+    OpenPalm,
+    /// the inside of the palm is facing the head
+    PalmFacingUser,
+    /// thumb extended and pointing up, the other fingers curled
+    ThumbsUp,
+}
+
+impl HandGesture {
+    pub const fn get_all_gestures() -> [HandGesture; 9] {
+        [
+            HandGesture::Pinch(Finger::Index),
+            HandGesture::Pinch(Finger::Middle),
+            HandGesture::Pinch(Finger::Ring),
+            HandGesture::Pinch(Finger::Little),
+            HandGesture::Grab,
+            HandGesture::Point,
+            HandGesture::OpenPalm,
+            HandGesture::PalmFacingUser,
+            HandGesture::ThumbsUp,
+        ]
+    }
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct GestureStarted {
+    pub hand: Hand,
+    pub gesture: HandGesture,
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct GestureEnded {
+    pub hand: Hand,
+    pub gesture: HandGesture,
+}
+
+/// Thresholds of the gesture recogniser, every gesture starts at the `start` threshold and only
+/// ends once it passes the `end` threshold so it doesn't flicker
+#[derive(Resource, Clone, Debug)]
+pub struct HandGestureConfig {
+    /// distance between the surfaces of the thumb tip and the finger tip at which a pinch starts
+    pub pinch_start_distance: f32,
+    /// distance at which a pinch ends, bigger than `pinch_start_distance`
+    pub pinch_end_distance: f32,
+    /// distance at which the pinch strength drops to 0.0
+    pub pinch_max_distance: f32,
+    /// curl from 0.0 to 1.0 above which a finger counts as curled
+    pub curled_start: f32,
+    pub curled_end: f32,
+    /// curl below which a finger counts as extended
+    pub extended_start: f32,
+    pub extended_end: f32,
+    /// cosine of the angle between the palm normal and the direction to the head
+    pub palm_facing_start: f32,
+    pub palm_facing_end: f32,
+    /// cosine of the angle between the thumb and up
+    pub thumbs_up_start: f32,
+    pub thumbs_up_end: f32,
+}
+
+impl Default for HandGestureConfig {
+    fn default() -> Self {
+        Self {
+            pinch_start_distance: 0.01,
+            pinch_end_distance: 0.025,
+            pinch_max_distance: 0.07,
+            curled_start: 0.65,
+            curled_end: 0.55,
+            extended_start: 0.25,
+            extended_end: 0.35,
+            palm_facing_start: 0.8,
+            palm_facing_end: 0.6,
+            thumbs_up_start: 0.8,
+            thumbs_up_end: 0.6,
+        }
+    }
+}
+
+/// The gesture state of a hand, the per finger arrays are indexed by [`Finger`]
+#[derive(Component, Clone, Debug, Default)]
+pub struct HandGestures {
+    /// 0.0 to 1.0, always 0.0 for the thumb
+    pub pinch_strength: [f32; 5],
+    /// 0.0 for a straight finger to 1.0 for a fully curled one
+    pub curl: [f32; 5],
+    pub pinching: [bool; 5],
+    pub curled: [bool; 5],
+    pub extended: [bool; 5],
+    /// never active together with `thumbs_up`
+    pub grab: bool,
+    pub point: bool,
+    pub open_palm: bool,
+    pub palm_facing_user: bool,
+    pub thumbs_up: bool,
+}
+
+impl HandGestures {
+    pub fn is_active(&self, gesture: HandGesture) -> bool {
+        match gesture {
+            HandGesture::Pinch(finger) => self.pinching[finger as usize],
+            HandGesture::Grab => self.grab,
+            HandGesture::Point => self.point,
+            HandGesture::OpenPalm => self.open_palm,
+            HandGesture::PalmFacingUser => self.palm_facing_user,
+            HandGesture::ThumbsUp => self.thumbs_up,
+        }
+    }
+    pub fn pinch_strength(&self, finger: Finger) -> f32 {
+        self.pinch_strength[finger as usize]
+    }
+    pub fn curl(&self, finger: Finger) -> f32 {
+        self.curl[finger as usize]
+    }
+}
+
+fn insert_hand_gestures(mut commands: Commands, hands: Option<Res<HandsResource>>) {
+    if let Some(hands) = hands {
+        commands
+            .entity(hands.left.palm)
+            .insert(HandGestures::default());
+        commands
+            .entity(hands.right.palm)
+            .insert(HandGestures::default());
+    }
+}
+
+/// value above the threshold, `start` has to be bigger than `end`
+fn above(active: bool, value: f32, start: f32, end: f32) -> bool {
+    match active {
+        true => value > end,
+        false => value > start,
+    }
+}
+
+/// value below the threshold, `start` has to be smaller than `end`
+fn below(active: bool, value: f32, start: f32, end: f32) -> bool {
+    match active {
+        true => value < end,
+        false => value < start,
+    }
+}
+
+/// sum of the bending angles between the finger segments, normalized to 90 degrees per joint
+fn finger_curl(bones: &[Transform; 26], finger: Finger) -> f32 {
+    let positions = finger
+        .get_bones()
+        .iter()
+        .map(|bone| bones[bone.get_index_from_bone()].translation)
+        .collect::<Vec<_>>();
+    let segments = positions
+        .windows(2)
+        .map(|w| w[1] - w[0])
+        .collect::<Vec<_>>();
+    let bend: f32 = segments
+        .windows(2)
+        .map(|w| w[0].angle_between(w[1]))
+        .filter(|a| a.is_finite())
+        .sum();
+    (bend / ((segments.len() - 1) as f32 * FRAC_PI_2)).clamp(0.0, 1.0)
+}
+
+fn update_gestures(
+    gestures: &mut HandGestures,
+    bones: &[Transform; 26],
+    radii: &[f32; 26],
+    head: Option<Vec3>,
+    config: &HandGestureConfig,
+) {
+    let thumb_tip = HandBone::ThumbTip.get_index_from_bone();
+    for finger in Finger::get_all_fingers() {
+        let i = finger as usize;
+        gestures.curl[i] = finger_curl(bones, finger);
+        gestures.curled[i] = above(
+            gestures.curled[i],
+            gestures.curl[i],
+            config.curled_start,
+            config.curled_end,
+        );
+        gestures.extended[i] = below(
+            gestures.extended[i],
+            gestures.curl[i],
+            config.extended_start,
+            config.extended_end,
+        );
+        if finger == Finger::Thumb {
+            continue;
+        }
+        let tip = finger.get_tip().get_index_from_bone();
+        let distance = bones[thumb_tip]
+            .translation
+            .distance(bones[tip].translation)
+            - radii[thumb_tip]
+            - radii[tip];
+        gestures.pinch_strength[i] = 1.0
+            - ((distance - config.pinch_start_distance)
+                / (config.pinch_max_distance - config.pinch_start_distance))
+                .clamp(0.0, 1.0);
+        gestures.pinching[i] = below(
+            gestures.pinching[i],
+            distance,
+            config.pinch_start_distance,
+            config.pinch_end_distance,
+        );
+    }
+    let [_, index, middle, ring, little] = gestures.curled;
+    let others_curled = middle && ring && little;
+    let fist = index && others_curled;
+    gestures.point = gestures.extended[Finger::Index as usize] && others_curled;
+    gestures.open_palm = gestures.extended.iter().all(|e| *e);
+
+    let thumb_dir = (bones[thumb_tip].translation
+        - bones[HandBone::ThumbProximal.get_index_from_bone()].translation)
+        .normalize_or_zero();
+    let thumb_up = above(
+        gestures.thumbs_up,
+        thumb_dir.dot(Vec3::Y),
+        config.thumbs_up_start,
+        config.thumbs_up_end,
+    );
+    gestures.thumbs_up = gestures.extended[Finger::Thumb as usize] && fist && thumb_up;
+    gestures.grab = fist && !gestures.thumbs_up;
+
+    let palm = &bones[HandBone::Palm.get_index_from_bone()];
+    // the Y axis of the palm points out of the back of the hand
+    let palm_normal = palm.rotation * Vec3::NEG_Y;
+    gestures.palm_facing_user = match head {
+        Some(head) => above(
+            gestures.palm_facing_user,
+            palm_normal.dot((head - palm.translation).normalize_or_zero()),
+            config.palm_facing_start,
+            config.palm_facing_end,
+        ),
+        None => false,
+    };
+}
+
+fn update_hand_gestures(
+    config: Res<HandGestureConfig>,
+    bone_query: Query<(&Transform, &Hand, &HandBone, &HandBoneRadius)>,
+    head_query: Query<&Transform, With<OpenXRHMD>>,
+    mut gesture_query: Query<(&Hand, &mut HandGestures)>,
+    mut started: EventWriter<GestureStarted>,
+    mut ended: EventWriter<GestureEnded>,
+) {
+    let mut bones = [[Transform::IDENTITY; 26]; 2];
+    let mut radii = [[0.0; 26]; 2];
+    for (transform, hand, bone, radius) in &bone_query {
+        bones[*hand as usize][bone.get_index_from_bone()] = *transform;
+        radii[*hand as usize][bone.get_index_from_bone()] = radius.0;
+    }
+    let head = head_query.get_single().ok().map(|t| t.translation);
+    for (hand, mut gestures) in &mut gesture_query {
+        let old = gestures.clone();
+        update_gestures(
+            &mut gestures,
+            &bones[*hand as usize],
+            &radii[*hand as usize],
+            head,
+            &config,
+        );
+        for gesture in HandGesture::get_all_gestures() {
+            match (old.is_active(gesture), gestures.is_active(gesture)) {
+                (false, true) => {
+                    started.send(GestureStarted {
+                        hand: *hand,
+                        gesture,
+                    });
+                }
+                (true, false) => {
+                    ended.send(GestureEnded {
+                        hand: *hand,
+                        gesture,
+                    });
+                }
+                _ => {}
+            }
+        }
+    }
+}
@@ -0,0 +1,167 @@
+use std::ptr;
+
+use bevy::prelude::*;
+use openxr as xr;
+use xr::sys::HandTrackingAimFlagsFB;
+
+use crate::{
+    xr_init::xr_only,
+    xr_input::{
+        bevy_input::update_xr_bevy_input,
+        controllers::{update_xr_controller_components, HandControllerInput, XrControllers},
+        trackers::{
+            update_open_xr_controllers, verify_quat, AimPose, OpenXRLeftController,
+            OpenXRRightController,
+        },
+        Hand, QuatConv, Vec3Conv,
+    },
+};
+
+use super::{hand_tracking::locate_hands, Finger};
+
+/// Turns tracked hands into virtual controllers with `XR_FB_hand_tracking_aim`, the extension
+/// has to be enabled with `XrExtensions::enable_hand_tracking_aim`.
+///
+/// The aim state is located together with the joints by [`locate_hands`]. While a hand is
+/// tracked its system aim pose is written to the [`AimPose`] of the controller entity and the
+/// index pinch strength is used as the trigger in [`XrControllers`], so ray interactors, menus
+/// and the bevy input keep working without controllers
+pub struct HandAimPlugin;
+
+impl Plugin for HandAimPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HandAimStates>();
+        app.add_systems(
+            PreUpdate,
+            update_hand_controller_input
+                .after(locate_hands)
+                .before(update_xr_controller_components)
+                .before(update_xr_bevy_input)
+                .run_if(resource_exists::<XrControllers>)
+                .run_if(xr_only()),
+        );
+        app.add_systems(
+            Update,
+            update_hand_aim_poses
+                .after(update_open_xr_controllers)
+                .run_if(xr_only()),
+        );
+    }
+}
+
+/// The aim state of a tracked hand as computed by the runtime
+#[derive(Clone, Copy, Debug, Default)]
+pub struct HandAimState {
+    /// the aim pose relative to the tracking root
+    pub aim_pose: Transform,
+    pub computed: bool,
+    /// the aim pose is valid and the hand is usable as a controller
+    pub valid: bool,
+    /// 0.0 to 1.0, indexed by [`Finger`], always 0.0 for the thumb
+    pub pinch_strength: [f32; 5],
+    /// indexed by [`Finger`], always false for the thumb
+    pub pinching: [bool; 5],
+    /// the system gesture (palm facing the head) is in progress
+    pub system_gesture: bool,
+    pub dominant_hand: bool,
+    /// the menu gesture of the non dominant hand was completed
+    pub menu_pressed: bool,
+}
+
+impl HandAimState {
+    pub(crate) fn from_raw(aim: &xr::sys::HandTrackingAimStateFB) -> Self {
+        let status = aim.status;
+        Self {
+            aim_pose: Transform::from_translation(aim.aim_pose.position.to_vec3())
+                .with_rotation(verify_quat(aim.aim_pose.orientation.to_quat())),
+            computed: status.contains(HandTrackingAimFlagsFB::COMPUTED),
+            valid: status.contains(HandTrackingAimFlagsFB::VALID),
+            pinch_strength: [
+                0.0,
+                aim.pinch_strength_index,
+                aim.pinch_strength_middle,
+                aim.pinch_strength_ring,
+                aim.pinch_strength_little,
+            ],
+            pinching: [
+                false,
+                status.contains(HandTrackingAimFlagsFB::INDEX_PINCHING),
+                status.contains(HandTrackingAimFlagsFB::MIDDLE_PINCHING),
+                status.contains(HandTrackingAimFlagsFB::RING_PINCHING),
+                status.contains(HandTrackingAimFlagsFB::LITTLE_PINCHING),
+            ],
+            system_gesture: status.contains(HandTrackingAimFlagsFB::SYSTEM_GESTURE),
+            dominant_hand: status.contains(HandTrackingAimFlagsFB::DOMINANT_HAND),
+            menu_pressed: status.contains(HandTrackingAimFlagsFB::MENU_PRESSED),
+        }
+    }
+    pub fn pinch_strength(&self, finger: Finger) -> f32 {
+        self.pinch_strength[finger as usize]
+    }
+    pub fn is_pinching(&self, finger: Finger) -> bool {
+        self.pinching[finger as usize]
+    }
+}
+
+/// The [`HandAimState`] of both hands, not valid while the hand isn't tracked
+#[derive(Resource, Clone, Copy, Debug, Default)]
+pub struct HandAimStates {
+    pub left: HandAimState,
+    pub right: HandAimState,
+}
+
+impl HandAimStates {
+    pub fn get(&self, hand: Hand) -> &HandAimState {
+        match hand {
+            Hand::Left => &self.left,
+            Hand::Right => &self.right,
+        }
+    }
+}
+
+/// an empty aim state to chain into the hand joint locate call
+pub(crate) fn raw_aim_state() -> xr::sys::HandTrackingAimStateFB {
+    xr::sys::HandTrackingAimStateFB {
+        ty: xr::sys::HandTrackingAimStateFB::TYPE,
+        next: ptr::null_mut(),
+        status: HandTrackingAimFlagsFB::EMPTY,
+        aim_pose: xr::Posef::IDENTITY,
+        pinch_strength_index: 0.0,
+        pinch_strength_middle: 0.0,
+        pinch_strength_ring: 0.0,
+        pinch_strength_little: 0.0,
+    }
+}
+
+/// feeds the aim states of valid hands into [`XrControllers`]
+fn update_hand_controller_input(
+    aim_states: Res<HandAimStates>,
+    mut controllers: ResMut<XrControllers>,
+) {
+    for hand in [Hand::Left, Hand::Right] {
+        let state = aim_states.get(hand);
+        *controllers.hand_input.get_mut(hand) = state.valid.then(|| HandControllerInput {
+            trigger: state.pinch_strength(Finger::Index),
+            trigger_touched: state.pinch_strength(Finger::Index) > 0.0,
+            menu_button: state.menu_pressed,
+        });
+    }
+}
+
+fn update_hand_aim_poses(
+    aim_states: Res<HandAimStates>,
+    mut controller_query: Query<
+        (&mut AimPose, Has<OpenXRLeftController>),
+        Or<(With<OpenXRLeftController>, With<OpenXRRightController>)>,
+    >,
+) {
+    for (mut aim_pose, is_left) in &mut controller_query {
+        let state = match is_left {
+            true => &aim_states.left,
+            false => &aim_states.right,
+        };
+        if state.valid {
+            aim_pose.0 = state.aim_pose;
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use bevy::prelude::*;
+use openxr as xr;
+
+use crate::{
+    xr_init::{xr_only, XrPostSetup},
+    xr_input::{Hand, Vec3Conv},
+};
+
+use super::{
+    common::HandBoneRadius, data_source::update_hand_data_sources, BoneTrackingStatus, Finger,
+    HandBone,
+};
+
+/// Adds a [`XrHandCapsule`] to every hand bone entity. The capsules come from
+/// `XR_FB_hand_tracking_capsules` while the hand is tracked, which has to be enabled with
+/// `XrExtensions::enable_hand_tracking_capsules`. Otherwise, and for emulated hands, they span
+/// from each joint to the next joint of the finger with the joint radius
+pub struct HandCapsulePlugin;
+
+impl Plugin for HandCapsulePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HandCapsuleStates>();
+        app.add_systems(XrPostSetup, insert_hand_capsules);
+        app.add_systems(
+            Update,
+            update_hand_capsules
+                .after(update_hand_data_sources)
+                .run_if(xr_only()),
+        );
+    }
+}
+
+/// Collision capsule of a hand bone, the points are relative to the bone entity so a collider
+/// can be added directly to it. Bones without a capsule, like the tips, get a sphere with both
+/// points at the origin
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq)]
+pub struct XrHandCapsule {
+    pub radius: f32,
+    pub points: [Vec3; 2],
+}
+
+/// capsules of a hand relative to the tracking root, indexed like [`HandBone`]
+pub type HandCapsules = [Option<XrHandCapsule>; 26];
+
+/// The runtime capsules of both hands, located together with the joints by
+/// [`locate_hands`](super::hand_tracking::locate_hands). `None` while the hand isn't tracked or
+/// without `XR_FB_hand_tracking_capsules`
+#[derive(Resource, Clone, Copy, Debug, Default)]
+pub struct HandCapsuleStates {
+    pub left: Option<HandCapsules>,
+    pub right: Option<HandCapsules>,
+}
+
+impl HandCapsuleStates {
+    pub fn get(&self, hand: Hand) -> Option<&HandCapsules> {
+        match hand {
+            Hand::Left => self.left.as_ref(),
+            Hand::Right => self.right.as_ref(),
+        }
+    }
+}
+
+/// an empty capsule state to chain into the hand joint locate call
+pub(crate) fn raw_capsules_state() -> xr::sys::HandTrackingCapsulesStateFB {
+    xr::sys::HandTrackingCapsulesStateFB {
+        ty: xr::sys::HandTrackingCapsulesStateFB::TYPE,
+        next: std::ptr::null_mut(),
+        capsules: [xr::sys::HandCapsuleFB {
+            points: [xr::Vector3f::default(); 2],
+            radius: 0.0,
+            joint: xr::sys::HandJointEXT::PALM,
+        }; 19],
+    }
+}
+
+/// the capsules of a located capsule state, the points are relative to the locate base space
+pub(crate) fn capsules_from_raw(state: &xr::sys::HandTrackingCapsulesStateFB) -> HandCapsules {
+    let mut capsules = [None; 26];
+    for capsule in state.capsules {
+        if let Some(c) = capsules.get_mut(capsule.joint.into_raw() as usize) {
+            *c = Some(XrHandCapsule {
+                radius: capsule.radius,
+                points: capsule.points.map(|p| p.to_vec3()),
+            });
+        }
+    }
+    capsules
+}
+
+fn insert_hand_capsules(mut commands: Commands, bone_query: Query<Entity, With<HandBone>>) {
+    for e in &bone_query {
+        commands.entity(e).insert(XrHandCapsule::default());
+    }
+}
+
+/// the joint after `bone` in its finger
+fn next_bone(bone: HandBone) -> Option<HandBone> {
+    Finger::get_all_fingers().iter().find_map(|finger| {
+        finger
+            .get_bones()
+            .windows(2)
+            .find(|w| w[0].get_index_from_bone() == bone.get_index_from_bone())
+            .map(|w| w[1])
+    })
+}
+
+#[allow(clippy::type_complexity)]
+fn update_hand_capsules(
+    runtime_capsules: Res<HandCapsuleStates>,
+    mut bone_query: Query<(
+        &Transform,
+        &Hand,
+        &HandBone,
+        &HandBoneRadius,
+        &BoneTrackingStatus,
+        &mut XrHandCapsule,
+    )>,
+) {
+    let mut bones = [[Vec3::ZERO; 26]; 2];
+    for (transform, hand, bone, ..) in &bone_query {
+        bones[*hand as usize][bone.get_index_from_bone()] = transform.translation;
+    }
+    for (transform, hand, bone, radius, status, mut capsule) in &mut bone_query {
+        let to_local = transform.compute_affine().inverse();
+        let runtime_capsule = match status {
+            BoneTrackingStatus::Tracked => runtime_capsules
+                .get(*hand)
+                .and_then(|c| c[bone.get_index_from_bone()]),
+            BoneTrackingStatus::Emulated => None,
+        };
+        *capsule = match runtime_capsule {
+            Some(c) => XrHandCapsule {
+                radius: c.radius,
+                points: c.points.map(|p| to_local.transform_point3(p)),
+            },
+            None => XrHandCapsule {
+                radius: radius.0,
+                points: [
+                    Vec3::ZERO,
+                    next_bone(*bone)
+                        .map(|next| {
+                            to_local
+                                .transform_point3(bones[*hand as usize][next.get_index_from_bone()])
+                        })
+                        .unwrap_or(Vec3::ZERO),
+                ],
+            },
+        };
+    }
+}
@@ -0,0 +1,244 @@
+use std::ptr;
+
+use bevy::{
+    prelude::*,
+    render::{
+        mesh::{
+            skinning::{SkinnedMesh, SkinnedMeshInverseBindposes},
+            Indices, PrimitiveTopology, VertexAttributeValues,
+        },
+        render_asset::RenderAssetUsages,
+        view::NoFrustumCulling,
+    },
+};
+use openxr as xr;
+
+use crate::{
+    cvt,
+    resources::XrInstance,
+    xr_init::{XrCleanup, XrPostSetup},
+    xr_input::{Hand, QuatConv, Vec2Conv, Vec3Conv},
+};
+
+use super::{common::HandsResource, hand_tracking::HandTrackingData, HandBone};
+
+/// Renders tracked hands with the skinned hand mesh of the runtime, using
+/// `XR_FB_hand_tracking_mesh`. The extension has to be enabled with
+/// `XrExtensions::enable_hand_tracking_mesh`. The joints of the [`SkinnedMesh`] are the hand
+/// bone entities in [`HandsResource`], so emulated hands are rendered too.
+///
+/// `XR_MSFT_hand_tracking_mesh` is not supported, it gives an unskinned mesh that has to be
+/// re-uploaded every frame.
+pub struct HandMeshPlugin;
+
+impl Plugin for HandMeshPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(XrPostSetup, spawn_hand_meshes);
+        app.add_systems(XrCleanup, despawn_hand_meshes);
+    }
+}
+
+/// Optional material for the hand meshes, has to be inserted before the session starts
+#[derive(Resource, Clone)]
+pub struct HandMeshMaterial(pub Handle<StandardMaterial>);
+
+#[derive(Component, Clone, Copy, Debug)]
+pub struct HandMesh(pub Hand);
+
+/// The hand mesh as returned by the runtime, vertices are in the bind pose
+#[derive(Clone, Debug, Default)]
+pub struct HandMeshData {
+    /// bind pose of every joint, indexed like [`HandBone`]
+    pub joint_bind_poses: Vec<Transform>,
+    pub joint_radii: Vec<f32>,
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    pub uvs: Vec<[f32; 2]>,
+    pub blend_indices: Vec<[u16; 4]>,
+    pub blend_weights: Vec<[f32; 4]>,
+    pub indices: Vec<u16>,
+}
+
+impl HandMeshData {
+    pub fn to_mesh(&self) -> Mesh {
+        let mut mesh = Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::RENDER_WORLD,
+        );
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, self.positions.clone());
+        mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, self.normals.clone());
+        mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, self.uvs.clone());
+        mesh.insert_attribute(
+            Mesh::ATTRIBUTE_JOINT_INDEX,
+            VertexAttributeValues::Uint16x4(self.blend_indices.clone()),
+        );
+        mesh.insert_attribute(Mesh::ATTRIBUTE_JOINT_WEIGHT, self.blend_weights.clone());
+        mesh.insert_indices(Indices::U16(self.indices.clone()));
+        mesh
+    }
+    pub fn inverse_bindposes(&self) -> SkinnedMeshInverseBindposes {
+        self.joint_bind_poses
+            .iter()
+            .map(|pose| pose.compute_matrix().inverse())
+            .collect::<Vec<_>>()
+            .into()
+    }
+}
+
+fn empty_hand_mesh() -> xr::sys::HandTrackingMeshFB {
+    xr::sys::HandTrackingMeshFB {
+        ty: xr::sys::HandTrackingMeshFB::TYPE,
+        next: ptr::null_mut(),
+        joint_capacity_input: 0,
+        joint_count_output: 0,
+        joint_bind_poses: ptr::null_mut(),
+        joint_radii: ptr::null_mut(),
+        joint_parents: ptr::null_mut(),
+        vertex_capacity_input: 0,
+        vertex_count_output: 0,
+        vertex_positions: ptr::null_mut(),
+        vertex_normals: ptr::null_mut(),
+        vertex_uvs: ptr::null_mut(),
+        vertex_blend_indices: ptr::null_mut(),
+        vertex_blend_weights: ptr::null_mut(),
+        index_capacity_input: 0,
+        index_count_output: 0,
+        indices: ptr::null_mut(),
+    }
+}
+
+pub fn get_hand_mesh(
+    instance: &XrInstance,
+    hand_tracker: &xr::HandTracker,
+) -> xr::Result<HandMeshData> {
+    let fp = instance
+        .exts()
+        .fb_hand_tracking_mesh
+        .ok_or(xr::sys::Result::ERROR_EXTENSION_NOT_PRESENT)?;
+    // first call only queries the buffer sizes
+    let mut mesh = empty_hand_mesh();
+    unsafe {
+        cvt((fp.get_hand_mesh)(hand_tracker.as_raw(), &mut mesh))?;
+    }
+    let joint_count = mesh.joint_count_output as usize;
+    let vertex_count = mesh.vertex_count_output as usize;
+    let index_count = mesh.index_count_output as usize;
+    let mut joint_bind_poses = vec![xr::Posef::IDENTITY; joint_count];
+    let mut joint_radii = vec![0.0; joint_count];
+    let mut joint_parents = vec![xr::sys::HandJointEXT::PALM; joint_count];
+    let mut positions = vec![xr::Vector3f::default(); vertex_count];
+    let mut normals = vec![xr::Vector3f::default(); vertex_count];
+    let mut uvs = vec![xr::Vector2f::default(); vertex_count];
+    let mut blend_indices = vec![xr::sys::Vector4sFB::default(); vertex_count];
+    let mut blend_weights = vec![xr::Vector4f::default(); vertex_count];
+    let mut indices = vec![0i16; index_count];
+    let mut mesh = xr::sys::HandTrackingMeshFB {
+        joint_capacity_input: joint_count as u32,
+        joint_bind_poses: joint_bind_poses.as_mut_ptr(),
+        joint_radii: joint_radii.as_mut_ptr(),
+        joint_parents: joint_parents.as_mut_ptr(),
+        vertex_capacity_input: vertex_count as u32,
+        vertex_positions: positions.as_mut_ptr(),
+        vertex_normals: normals.as_mut_ptr(),
+        vertex_uvs: uvs.as_mut_ptr(),
+        vertex_blend_indices: blend_indices.as_mut_ptr(),
+        vertex_blend_weights: blend_weights.as_mut_ptr(),
+        index_capacity_input: index_count as u32,
+        indices: indices.as_mut_ptr(),
+        ..empty_hand_mesh()
+    };
+    unsafe {
+        cvt((fp.get_hand_mesh)(hand_tracker.as_raw(), &mut mesh))?;
+    }
+    Ok(HandMeshData {
+        joint_bind_poses: joint_bind_poses
+            .iter()
+            .map(|pose| {
+                Transform::from_translation(pose.position.to_vec3())
+                    .with_rotation(pose.orientation.to_quat())
+            })
+            .collect(),
+        joint_radii,
+        positions: positions.iter().map(|v| v.to_vec3().to_array()).collect(),
+        normals: normals.iter().map(|v| v.to_vec3().to_array()).collect(),
+        uvs: uvs.iter().map(|v| v.to_vec2().to_array()).collect(),
+        blend_indices: blend_indices
+            .iter()
+            .map(|v| [v.x as u16, v.y as u16, v.z as u16, v.w as u16])
+            .collect(),
+        blend_weights: blend_weights.iter().map(|v| [v.x, v.y, v.z, v.w]).collect(),
+        indices: indices.into_iter().map(|i| i as u16).collect(),
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+fn spawn_hand_meshes(
+    mut commands: Commands,
+    instance: Res<XrInstance>,
+    hand_tracking: Option<Res<HandTrackingData>>,
+    hands_resource: Option<Res<HandsResource>>,
+    hand_material: Option<Res<HandMeshMaterial>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut bindposes: ResMut<Assets<SkinnedMeshInverseBindposes>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let (hand_tracking, hands_resource) = match (hand_tracking, hands_resource) {
+        (Some(t), Some(h)) => (t, h),
+        _ => return,
+    };
+    if instance.exts().fb_hand_tracking_mesh.is_none() {
+        return;
+    }
+    let material = match hand_material {
+        Some(m) => m.0.clone(),
+        None => materials.add(StandardMaterial {
+            base_color: Color::rgb(0.8, 0.7, 0.6),
+            perceptual_roughness: 0.7,
+            ..default()
+        }),
+    };
+    for hand in [Hand::Left, Hand::Right] {
+        let data = match get_hand_mesh(&instance, hand_tracking.hand_tracker(hand)) {
+            Ok(data) => data,
+            Err(err) => {
+                warn!("unable to get {:?} hand mesh: {}", hand, err);
+                continue;
+            }
+        };
+        let bones = HandBone::get_all_bones();
+        if data.joint_bind_poses.len() > bones.len() {
+            warn!("{:?} hand mesh has too many joints", hand);
+            continue;
+        }
+        let hand_res = match hand {
+            Hand::Left => &hands_resource.left,
+            Hand::Right => &hands_resource.right,
+        };
+        let joints = bones
+            .iter()
+            .take(data.joint_bind_poses.len())
+            .map(|bone| hand_res.get_bone(*bone))
+            .collect();
+        commands.spawn((
+            PbrBundle {
+                mesh: meshes.add(data.to_mesh()),
+                material: material.clone(),
+                ..default()
+            },
+            SkinnedMesh {
+                inverse_bindposes: bindposes.add(data.inverse_bindposes()),
+                joints,
+            },
+            // the bounds of the mesh don't follow the joints
+            NoFrustumCulling,
+            Name::new(format!("{:?} Hand Mesh", hand)),
+            HandMesh(hand),
+        ));
+    }
+}
+
+fn despawn_hand_meshes(mut commands: Commands, mesh_query: Query<Entity, With<HandMesh>>) {
+    for e in &mesh_query {
+        commands.entity(e).despawn_recursive();
+    }
+}
@@ -0,0 +1,381 @@
+use std::{fmt, path::Path};
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    utils::BoxedFuture,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{xr_init::xr_only, xr_input::Hand};
+
+use super::{
+    data_source::{update_hand_data_sources, HandDataSource, HandDataSources},
+    hand_tracking::HandJoints,
+    HandBone,
+};
+
+/// Registers the [`HandPose`] asset, loaded from `.handpose.ron` files, and matches tracked and
+/// emulated hands against the poses in the [`HandPoseLibrary`], sending [`HandPoseMatched`]
+/// events
+pub struct HandPosePlugin;
+
+impl Plugin for HandPosePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<HandPose>();
+        app.register_asset_loader(HandPoseLoader);
+        app.init_resource::<HandPoseLibrary>();
+        app.add_event::<HandPoseMatched>();
+        app.add_systems(
+            Update,
+            match_hand_poses
+                .after(update_hand_data_sources)
+                .run_if(xr_only()),
+        );
+    }
+}
+
+/// A static hand pose, the joint transforms are relative to the wrist and indexed like
+/// [`HandBone`]
+#[derive(Asset, TypePath, Clone, Debug)]
+pub struct HandPose {
+    /// the hand the pose was captured from, it is mirrored when compared to the other hand
+    pub hand: Hand,
+    pub joints: [Transform; 26],
+    /// how much each joint counts in [`HandPose::distance`]
+    pub weights: [f32; 26],
+}
+
+impl HandPose {
+    /// the wrist and palm don't count by default since they barely move relative to the wrist
+    pub fn default_weights() -> [f32; 26] {
+        let mut weights = [1.0; 26];
+        weights[HandBone::Palm.get_index_from_bone()] = 0.0;
+        weights[HandBone::Wrist.get_index_from_bone()] = 0.0;
+        weights
+    }
+
+    /// captures a pose from joint transforms in any space, indexed like [`HandBone`]
+    pub fn from_transforms(hand: Hand, transforms: &[Transform; 26]) -> Self {
+        let wrist = transforms[HandBone::Wrist.get_index_from_bone()];
+        let inverse_rotation = wrist.rotation.inverse();
+        Self {
+            hand,
+            joints: transforms.map(|t| Transform {
+                translation: inverse_rotation * (t.translation - wrist.translation),
+                rotation: inverse_rotation * t.rotation,
+                scale: Vec3::ONE,
+            }),
+            weights: Self::default_weights(),
+        }
+    }
+
+    /// captures a pose from live hand tracking data
+    pub fn from_hand_joints(hand: Hand, joints: &HandJoints) -> Self {
+        let transforms = std::array::from_fn(|i| {
+            let joint = &joints.inner()[i];
+            Transform::from_translation(joint.position).with_rotation(joint.orientation)
+        });
+        Self::from_transforms(hand, &transforms)
+    }
+
+    pub fn with_weight(mut self, bone: HandBone, weight: f32) -> Self {
+        self.weights[bone.get_index_from_bone()] = weight;
+        self
+    }
+
+    /// the pose mirrored onto the other hand
+    pub fn mirrored(&self) -> Self {
+        Self {
+            hand: match self.hand {
+                Hand::Left => Hand::Right,
+                Hand::Right => Hand::Left,
+            },
+            joints: self.joints.map(|t| Transform {
+                translation: t.translation * Vec3::new(-1.0, 1.0, 1.0),
+                rotation: Quat::from_xyzw(t.rotation.x, -t.rotation.y, -t.rotation.z, t.rotation.w),
+                scale: t.scale,
+            }),
+            weights: self.weights,
+        }
+    }
+
+    /// the weighted mean distance in meters between the joints of both poses, using the weights
+    /// of `self`. `other` is mirrored first if it was captured from the other hand
+    pub fn distance(&self, other: &HandPose) -> f32 {
+        let mirrored;
+        let other = match other.hand == self.hand {
+            true => other,
+            false => {
+                mirrored = other.mirrored();
+                &mirrored
+            }
+        };
+        let mut distance = 0.0;
+        let mut total_weight = 0.0;
+        for i in 0..26 {
+            distance += self.weights[i]
+                * self.joints[i]
+                    .translation
+                    .distance(other.joints[i].translation);
+            total_weight += self.weights[i];
+        }
+        match total_weight > 0.0 {
+            true => distance / total_weight,
+            false => 0.0,
+        }
+    }
+
+    pub fn from_ron(ron: &str) -> Result<Self, HandPoseError> {
+        ron::from_str::<HandPoseRon>(ron)?.try_into()
+    }
+
+    pub fn to_ron(&self) -> Result<String, HandPoseError> {
+        let ron = HandPoseRon::from(self);
+        Ok(ron::ser::to_string_pretty(
+            &ron,
+            ron::ser::PrettyConfig::default(),
+        )?)
+    }
+
+    /// writes the pose to a RON file, which can be loaded again with the [`AssetServer`]
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), HandPoseError> {
+        std::fs::write(path, self.to_ron()?)?;
+        Ok(())
+    }
+}
+
+/// the file format of a [`HandPose`]
+#[derive(Serialize, Deserialize)]
+struct HandPoseRon {
+    hand: Hand,
+    /// translation and rotation of each joint
+    joints: Vec<([f32; 3], [f32; 4])>,
+    #[serde(default)]
+    weights: Option<Vec<f32>>,
+}
+
+impl From<&HandPose> for HandPoseRon {
+    fn from(pose: &HandPose) -> Self {
+        Self {
+            hand: pose.hand,
+            joints: pose
+                .joints
+                .iter()
+                .map(|t| (t.translation.to_array(), t.rotation.to_array()))
+                .collect(),
+            weights: Some(pose.weights.to_vec()),
+        }
+    }
+}
+
+impl TryFrom<HandPoseRon> for HandPose {
+    type Error = HandPoseError;
+
+    fn try_from(ron: HandPoseRon) -> Result<Self, Self::Error> {
+        let joints: [_; 26] = ron
+            .joints
+            .try_into()
+            .map_err(|j: Vec<_>| HandPoseError::JointCount(j.len()))?;
+        let weights = match ron.weights {
+            Some(weights) => weights
+                .try_into()
+                .map_err(|w: Vec<_>| HandPoseError::JointCount(w.len()))?,
+            None => HandPose::default_weights(),
+        };
+        Ok(Self {
+            hand: ron.hand,
+            joints: joints.map(|(translation, rotation)| Transform {
+                translation: Vec3::from_array(translation),
+                rotation: Quat::from_array(rotation).normalize(),
+                scale: Vec3::ONE,
+            }),
+            weights,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub enum HandPoseError {
+    Io(std::io::Error),
+    Parse(ron::error::SpannedError),
+    Serialize(ron::Error),
+    /// the file doesn't have exactly 26 joints or weights
+    JointCount(usize),
+}
+
+impl fmt::Display for HandPoseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HandPoseError::Io(e) => write!(f, "could not read or write hand pose: {e}"),
+            HandPoseError::Parse(e) => write!(f, "could not parse hand pose: {e}"),
+            HandPoseError::Serialize(e) => write!(f, "could not serialize hand pose: {e}"),
+            HandPoseError::JointCount(count) => {
+                write!(f, "hand pose has {count} joints instead of 26")
+            }
+        }
+    }
+}
+
+impl std::error::Error for HandPoseError {}
+
+impl From<std::io::Error> for HandPoseError {
+    fn from(e: std::io::Error) -> Self {
+        HandPoseError::Io(e)
+    }
+}
+
+impl From<ron::error::SpannedError> for HandPoseError {
+    fn from(e: ron::error::SpannedError) -> Self {
+        HandPoseError::Parse(e)
+    }
+}
+
+impl From<ron::Error> for HandPoseError {
+    fn from(e: ron::Error) -> Self {
+        HandPoseError::Serialize(e)
+    }
+}
+
+#[derive(Default)]
+pub struct HandPoseLoader;
+
+impl AssetLoader for HandPoseLoader {
+    type Asset = HandPose;
+    type Settings = ();
+    type Error = HandPoseError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a Self::Settings,
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            ron::de::from_bytes::<HandPoseRon>(&bytes)?.try_into()
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["handpose.ron"]
+    }
+}
+
+/// Sent when a hand starts matching a pose of the [`HandPoseLibrary`]
+#[derive(Event, Clone, Debug)]
+pub struct HandPoseMatched {
+    pub hand: Hand,
+    pub name: String,
+    pub pose: Handle<HandPose>,
+    pub distance: f32,
+}
+
+struct LibraryPose {
+    name: String,
+    pose: Handle<HandPose>,
+}
+
+/// The poses both hands are matched against, the closest pose within
+/// `match_distance` is matched and stays matched until its distance is above
+/// `unmatch_distance`
+#[derive(Resource)]
+pub struct HandPoseLibrary {
+    pub match_distance: f32,
+    pub unmatch_distance: f32,
+    poses: Vec<LibraryPose>,
+    /// index of the matched pose of each hand
+    matched: [Option<usize>; 2],
+}
+
+impl Default for HandPoseLibrary {
+    fn default() -> Self {
+        Self {
+            match_distance: 0.015,
+            unmatch_distance: 0.025,
+            poses: Vec::new(),
+            matched: [None; 2],
+        }
+    }
+}
+
+impl HandPoseLibrary {
+    pub fn add(&mut self, name: impl Into<String>, pose: Handle<HandPose>) {
+        self.poses.push(LibraryPose {
+            name: name.into(),
+            pose,
+        });
+    }
+    pub fn remove(&mut self, name: &str) {
+        self.poses.retain(|p| p.name != name);
+        self.matched = [None; 2];
+    }
+    /// the name of the pose the hand currently matches
+    pub fn matched(&self, hand: Hand) -> Option<&str> {
+        self.matched[hand as usize].map(|i| self.poses[i].name.as_str())
+    }
+}
+
+fn match_hand_poses(
+    mut library: ResMut<HandPoseLibrary>,
+    poses: Res<Assets<HandPose>>,
+    sources: Option<Res<HandDataSources>>,
+    bone_query: Query<(&Transform, &Hand, &HandBone)>,
+    mut matched_events: EventWriter<HandPoseMatched>,
+) {
+    if library.poses.is_empty() {
+        return;
+    }
+    let mut transforms = [[Transform::IDENTITY; 26]; 2];
+    for (transform, hand, bone) in &bone_query {
+        transforms[*hand as usize][bone.get_index_from_bone()] = *transform;
+    }
+    for hand in [Hand::Left, Hand::Right] {
+        // simulated hands are always open, only tracked and emulated hands follow the fingers
+        // of the user
+        let has_data = sources.as_ref().map_or(true, |s| {
+            matches!(
+                s.get(hand),
+                HandDataSource::Tracked | HandDataSource::Emulated
+            )
+        });
+        if !has_data {
+            library.matched[hand as usize] = None;
+            continue;
+        }
+        // a blending hand is in between two sources, it keeps its match until the blend is done
+        if sources.as_ref().is_some_and(|s| s.is_blending(hand)) {
+            continue;
+        }
+        let current = HandPose::from_transforms(hand, &transforms[hand as usize]);
+        let distances = library
+            .poses
+            .iter()
+            .map(|p| poses.get(&p.pose).map(|pose| pose.distance(&current)))
+            .collect::<Vec<_>>();
+        let previous = library.matched[hand as usize];
+        let still_matched =
+            previous.filter(|i| distances[*i].is_some_and(|d| d <= library.unmatch_distance));
+        let best = distances
+            .iter()
+            .enumerate()
+            .filter_map(|(i, d)| d.map(|d| (i, d)))
+            .filter(|(_, d)| *d <= library.match_distance)
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+        let matched = match (still_matched, best) {
+            (Some(i), _) => Some(i),
+            (None, Some((i, _))) => Some(i),
+            (None, None) => None,
+        };
+        if let Some(i) = matched.filter(|i| Some(*i) != previous) {
+            matched_events.send(HandPoseMatched {
+                hand,
+                name: library.poses[i].name.clone(),
+                pose: library.poses[i].pose.clone(),
+                distance: distances[i].unwrap_or_default(),
+            });
+        }
+        library.matched[hand as usize] = matched;
+    }
+}
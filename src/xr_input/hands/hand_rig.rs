@@ -0,0 +1,210 @@
+use bevy::{math::Affine3A, prelude::*, transform::TransformSystem};
+
+use crate::{
+    xr_init::xr_only,
+    xr_input::{trackers::OpenXRTrackingRoot, Hand},
+};
+
+use super::HandBone;
+
+/// Drives the joints of skinned hand models with a [`HandRig`] from the hand bone entities,
+/// for tracked and emulated hands alike
+pub struct HandRigPlugin;
+
+impl Plugin for HandRigPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, resolve_hand_rigs);
+        app.add_systems(
+            PostUpdate,
+            update_hand_rigs
+                .before(TransformSystem::TransformPropagate)
+                .run_if(xr_only()),
+        );
+    }
+}
+
+/// Maps the joints of a loaded skinned hand model onto [`HandBone`]s, add it to the entity the
+/// glTF scene is spawned on. The joints are looked up by their [`Name`] once the scene has
+/// spawned.
+///
+/// Only the rotation of the joints is driven, except for the top most mapped joint (usually the
+/// wrist) which is moved to the tracked position too, so the proportions of the model are kept.
+#[derive(Component, Clone, Debug)]
+pub struct HandRig {
+    pub hand: Hand,
+    pub joints: Vec<HandRigJoint>,
+    /// Use a model of the other hand, the local X axis of every joint is mirrored. The entity
+    /// has to be scaled by -1 on X so the mesh is mirrored too
+    pub mirror: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct HandRigJoint {
+    pub bone: HandBone,
+    /// name of the joint node in the glTF file
+    pub name: String,
+    /// rotation applied on top of the tracked rotation, to match the joint axes of the model to
+    /// the OpenXR ones (-Z along the finger, +Y out of the back of the hand)
+    pub offset: Quat,
+}
+
+impl HandRig {
+    pub fn new(hand: Hand) -> Self {
+        Self {
+            hand,
+            joints: Vec::new(),
+            mirror: false,
+        }
+    }
+    pub fn with_joint(mut self, bone: HandBone, name: impl Into<String>, offset: Quat) -> Self {
+        self.joints.push(HandRigJoint {
+            bone,
+            name: name.into(),
+            offset,
+        });
+        self
+    }
+    pub fn mirrored(mut self) -> Self {
+        self.mirror = true;
+        self
+    }
+}
+
+struct ResolvedJoint {
+    entity: Entity,
+    bone: HandBone,
+    offset: Quat,
+    /// index of the closest mapped ancestor
+    parent: Option<usize>,
+    /// the unmapped nodes between the mapped ancestor (or the rig entity) and the joint, top
+    /// most first
+    intermediate: Vec<Entity>,
+    bind_translation: Vec3,
+    bind_scale: Vec3,
+}
+
+/// The joint entities of a [`HandRig`], parents always come before their children
+#[derive(Component)]
+pub struct ResolvedHandRig {
+    joints: Vec<ResolvedJoint>,
+}
+
+#[allow(clippy::type_complexity)]
+fn resolve_hand_rigs(
+    mut commands: Commands,
+    rig_query: Query<(Entity, &HandRig), Or<(Without<ResolvedHandRig>, Changed<HandRig>)>>,
+    children_query: Query<&Children>,
+    parent_query: Query<&Parent>,
+    joint_query: Query<(&Name, &Transform)>,
+) {
+    for (rig_entity, rig) in &rig_query {
+        let descendants = children_query
+            .iter_descendants(rig_entity)
+            .collect::<Vec<_>>();
+        // the scene hasn't spawned yet
+        if descendants.is_empty() {
+            continue;
+        }
+        let mut found = Vec::new();
+        for joint in &rig.joints {
+            let entity = descendants.iter().copied().find(|e| {
+                joint_query
+                    .get(*e)
+                    .is_ok_and(|(name, _)| name.as_str() == joint.name)
+            });
+            match entity {
+                Some(entity) => found.push((entity, joint)),
+                None => warn!("hand rig joint {} not found", joint.name),
+            }
+        }
+        // ancestors of each joint up to the rig entity, closest first
+        let ancestors = |entity: Entity| {
+            parent_query
+                .iter_ancestors(entity)
+                .take_while(move |e| *e != rig_entity)
+        };
+        found.sort_by_key(|(entity, _)| ancestors(*entity).count());
+        let mut joints: Vec<ResolvedJoint> = Vec::with_capacity(found.len());
+        for (entity, joint) in found {
+            let mut parent = None;
+            let mut intermediate = Vec::new();
+            for ancestor in ancestors(entity) {
+                if let Some(p) = joints.iter().position(|j| j.entity == ancestor) {
+                    parent = Some(p);
+                    break;
+                }
+                intermediate.push(ancestor);
+            }
+            intermediate.reverse();
+            let (_, transform) = joint_query.get(entity).unwrap();
+            joints.push(ResolvedJoint {
+                entity,
+                bone: joint.bone,
+                offset: joint.offset,
+                parent,
+                intermediate,
+                bind_translation: transform.translation,
+                bind_scale: transform.scale,
+            });
+        }
+        commands
+            .entity(rig_entity)
+            .insert(ResolvedHandRig { joints });
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn update_hand_rigs(
+    rig_query: Query<(Entity, &HandRig, &ResolvedHandRig)>,
+    bone_query: Query<(&Transform, &Hand, &HandBone)>,
+    mut joint_query: Query<&mut Transform, Without<HandBone>>,
+    global_query: Query<&GlobalTransform>,
+    root_query: Query<&GlobalTransform, With<OpenXRTrackingRoot>>,
+) {
+    let root = root_query
+        .get_single()
+        .map(|r| r.affine())
+        .unwrap_or(Affine3A::IDENTITY);
+    let mut bones = [[None; 26]; 2];
+    for (transform, hand, bone) in &bone_query {
+        bones[*hand as usize][bone.get_index_from_bone()] = Some(
+            root * Affine3A::from_rotation_translation(transform.rotation, transform.translation),
+        );
+    }
+    for (rig_entity, rig, resolved) in &rig_query {
+        let mirror = match rig.mirror {
+            true => Affine3A::from_scale(Vec3::new(-1.0, 1.0, 1.0)),
+            false => Affine3A::IDENTITY,
+        };
+        let mut world = vec![Affine3A::IDENTITY; resolved.joints.len()];
+        for (i, joint) in resolved.joints.iter().enumerate() {
+            let mut parent_world = match joint.parent {
+                Some(p) => world[p],
+                None => global_query
+                    .get(rig_entity)
+                    .map(|g| g.affine())
+                    .unwrap_or(Affine3A::IDENTITY),
+            };
+            for node in &joint.intermediate {
+                if let Ok(transform) = joint_query.get(*node) {
+                    parent_world *= transform.compute_affine();
+                }
+            }
+            let Ok(mut transform) = joint_query.get_mut(joint.entity) else {
+                continue;
+            };
+            if let Some(tracked) = bones[rig.hand as usize][joint.bone.get_index_from_bone()] {
+                let target = tracked * Affine3A::from_quat(joint.offset) * mirror;
+                let (_, rotation, translation) =
+                    (parent_world.inverse() * target).to_scale_rotation_translation();
+                transform.rotation = rotation;
+                transform.translation = match joint.parent {
+                    Some(_) => joint.bind_translation,
+                    None => translation,
+                };
+                transform.scale = joint.bind_scale;
+            }
+            world[i] = parent_world * transform.compute_affine();
+        }
+    }
+}
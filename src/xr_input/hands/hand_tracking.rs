@@ -1,15 +1,30 @@
+use std::{ffi::c_void, ptr};
+
 use bevy::prelude::*;
-use openxr::{HandTracker, Result, SpaceLocationFlags};
+use openxr::{
+    HandJointLocation, HandJointVelocity, HandTracker, Result, SpaceLocationFlags,
+    SpaceVelocityFlags,
+};
 
 use super::common::HandBoneRadius;
 use crate::{
+    cvt,
     input::XrInput,
     resources::{XrFrameState, XrSession},
     xr_init::xr_only,
-    xr_input::{hands::HandBone, Hand, QuatConv, Vec3Conv},
+    xr_input::{
+        hands::HandBone,
+        trackers::{TrackingStatusParams, XrTrackingStatus, XrVelocity},
+        Hand, QuatConv, Vec3Conv,
+    },
 };
 
-use super::BoneTrackingStatus;
+use super::{
+    data_source::{HandDataSource, HandDataSources},
+    hand_aim::{raw_aim_state, HandAimState, HandAimStates},
+    hand_capsules::{capsules_from_raw, raw_capsules_state, HandCapsuleStates, HandCapsules},
+    BoneTrackingStatus,
+};
 
 #[derive(Resource, PartialEq)]
 pub enum DisableHandTracking {
@@ -19,10 +34,83 @@ pub enum DisableHandTracking {
 }
 pub struct HandTrackingPlugin;
 
+/// How far the hand joints may move while the user holds a controller, only used when
+/// `XR_EXT_hand_joints_motion_range` is enabled with
+/// `XrExtensions::enable_hand_joints_motion_range`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HandMotionRange {
+    /// the actual pose of the hand, which may go through the controller
+    #[default]
+    Unobstructed,
+    /// the hand wraps around the controller
+    ConformingToController,
+}
+
+impl HandMotionRange {
+    fn to_xr(self) -> openxr::sys::HandJointsMotionRangeEXT {
+        match self {
+            HandMotionRange::Unobstructed => openxr::sys::HandJointsMotionRangeEXT::UNOBSTRUCTED,
+            HandMotionRange::ConformingToController => {
+                openxr::sys::HandJointsMotionRangeEXT::CONFORMING_TO_CONTROLLER
+            }
+        }
+    }
+}
+
+/// Makes [`locate_hands`] locate the joint velocities too, [`update_hand_bones`] writes them
+/// to the [`XrVelocity`] of the hand bones, which stays invalid otherwise
+#[derive(Resource, Clone, Copy, Debug, Default)]
+pub struct EnableHandJointVelocities;
+
+/// What [`HandTrackingRef::locate`] chains into the hand joint locate call
+#[derive(Clone, Copy, Debug, Default)]
+pub struct HandLocateOptions {
+    pub motion_range: HandMotionRange,
+    pub velocities: bool,
+    /// locates the [`HandAimState`] too, ignored when `XR_FB_hand_tracking_aim` isn't enabled
+    pub aim: bool,
+    /// locates the [`HandCapsules`] too, ignored when `XR_FB_hand_tracking_capsules` isn't
+    /// enabled
+    pub capsules: bool,
+}
+
+/// The result of [`HandTrackingRef::locate`]
+#[derive(Debug)]
+pub struct LocatedHand {
+    pub joints: HandJoints,
+    /// only located when [`HandLocateOptions::aim`] is set
+    pub aim: Option<HandAimState>,
+    /// only located when [`HandLocateOptions::capsules`] is set
+    pub capsules: Option<HandCapsules>,
+}
+
+/// The [`HandMotionRange`] used by [`locate_hands`] for each hand
+#[derive(Resource, Clone, Copy, Debug, Default)]
+pub struct HandMotionRanges {
+    pub left: HandMotionRange,
+    pub right: HandMotionRange,
+}
+
+impl HandMotionRanges {
+    pub fn get(&self, hand: Hand) -> HandMotionRange {
+        match hand {
+            Hand::Left => self.left,
+            Hand::Right => self.right,
+        }
+    }
+    pub fn set(&mut self, hand: Hand, range: HandMotionRange) {
+        match hand {
+            Hand::Left => self.left = range,
+            Hand::Right => self.right = range,
+        }
+    }
+}
+
 #[derive(Resource)]
 pub struct HandTrackingData {
     left_hand: HandTracker,
     right_hand: HandTracker,
+    instance: openxr::Instance,
 }
 
 impl HandTrackingData {
@@ -32,8 +120,15 @@ impl HandTrackingData {
         Ok(HandTrackingData {
             left_hand: left,
             right_hand: right,
+            instance: session.instance().clone(),
         })
     }
+    pub fn hand_tracker(&self, hand: Hand) -> &HandTracker {
+        match hand {
+            Hand::Left => &self.left_hand,
+            Hand::Right => &self.right_hand,
+        }
+    }
     pub fn get_ref<'a>(
         &'a self,
         input: &'a XrInput,
@@ -61,6 +156,56 @@ pub struct HandJoint {
     pub orientation_valid: bool,
     pub orientation_tracked: bool,
     pub radius: f32,
+    /// only filled when the velocities were located, see [`HandLocateOptions::velocities`]
+    pub linear_velocity: Vec3,
+    pub linear_velocity_valid: bool,
+    pub angular_velocity: Vec3,
+    pub angular_velocity_valid: bool,
+}
+
+impl HandJoint {
+    fn new(location: &HandJointLocation, velocity: Option<&HandJointVelocity>) -> Self {
+        let velocity = velocity
+            .map(|v| XrVelocity::new(v.velocity_flags, v.linear_velocity, v.angular_velocity))
+            .unwrap_or_default();
+        HandJoint {
+            position: location.pose.position.to_vec3(),
+            orientation: location.pose.orientation.to_quat(),
+            position_valid: location
+                .location_flags
+                .contains(SpaceLocationFlags::POSITION_VALID),
+            position_tracked: location
+                .location_flags
+                .contains(SpaceLocationFlags::POSITION_TRACKED),
+            orientation_valid: location
+                .location_flags
+                .contains(SpaceLocationFlags::ORIENTATION_VALID),
+            orientation_tracked: location
+                .location_flags
+                .contains(SpaceLocationFlags::ORIENTATION_TRACKED),
+            radius: location.radius,
+            linear_velocity: velocity.linear,
+            linear_velocity_valid: velocity.linear_valid,
+            angular_velocity: velocity.angular,
+            angular_velocity_valid: velocity.angular_valid,
+        }
+    }
+    pub fn tracking_status(&self) -> XrTrackingStatus {
+        XrTrackingStatus {
+            position_valid: self.position_valid,
+            position_tracked: self.position_tracked,
+            orientation_valid: self.orientation_valid,
+            orientation_tracked: self.orientation_tracked,
+        }
+    }
+    pub fn velocity(&self) -> XrVelocity {
+        XrVelocity {
+            linear: self.linear_velocity,
+            linear_valid: self.linear_velocity_valid,
+            angular: self.angular_velocity,
+            angular_valid: self.angular_velocity_valid,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -80,133 +225,285 @@ impl HandJoints {
 }
 
 impl<'a> HandTrackingRef<'a> {
+    /// joint poses relative to the tracking root, without velocities
     pub fn get_poses(&self, side: Hand) -> Option<HandJoints> {
         self.input
             .stage
             .locate_hand_joints(
-                match side {
-                    Hand::Left => &self.tracking.left_hand,
-                    Hand::Right => &self.tracking.right_hand,
-                },
+                self.tracking.hand_tracker(side),
                 self.frame_state.predicted_display_time,
             )
             .unwrap()
-            .map(|joints| {
-                joints
-                    .into_iter()
-                    .map(|joint| HandJoint {
-                        position: joint.pose.position.to_vec3(),
-                        orientation: joint.pose.orientation.to_quat(),
-                        position_valid: joint
-                            .location_flags
-                            .contains(SpaceLocationFlags::POSITION_VALID),
-                        position_tracked: joint
-                            .location_flags
-                            .contains(SpaceLocationFlags::POSITION_TRACKED),
-                        orientation_valid: joint
-                            .location_flags
-                            .contains(SpaceLocationFlags::ORIENTATION_VALID),
-                        orientation_tracked: joint
-                            .location_flags
-                            .contains(SpaceLocationFlags::ORIENTATION_TRACKED),
-                        radius: joint.radius,
-                    })
-                    .collect::<Vec<HandJoint>>()
-                    .try_into()
-                    .unwrap()
+            .map(|joints| HandJoints {
+                inner: std::array::from_fn(|i| HandJoint::new(&joints[i], None)),
             })
-            .map(|joints| HandJoints { inner: joints })
     }
+    /// joint poses and velocities relative to the tracking root, `motion_range` is ignored when
+    /// `XR_EXT_hand_joints_motion_range` isn't enabled
+    pub fn get_poses_with_velocities(
+        &self,
+        side: Hand,
+        motion_range: HandMotionRange,
+    ) -> Option<HandJoints> {
+        self.locate(
+            side,
+            HandLocateOptions {
+                motion_range,
+                velocities: true,
+                ..default()
+            },
+        )
+        .map(|located| located.joints)
+    }
+    /// joint poses relative to the tracking root, with everything in `options` chained into the
+    /// same locate call. Falls back to [`HandTrackingRef::get_poses`] when nothing is chained
+    pub fn locate(&self, side: Hand, options: HandLocateOptions) -> Option<LocatedHand> {
+        let exts = self.tracking.instance.exts();
+        let motion_range = options.motion_range != HandMotionRange::Unobstructed
+            && exts.ext_hand_joints_motion_range.is_some();
+        let aim = options.aim && exts.fb_hand_tracking_aim.is_some();
+        let capsules = options.capsules && exts.fb_hand_tracking_capsules.is_some();
+        if !motion_range && !options.velocities && !aim && !capsules {
+            return self.get_poses(side).map(|joints| LocatedHand {
+                joints,
+                aim: None,
+                capsules: None,
+            });
+        }
+        let mut next: *mut c_void = ptr::null_mut();
+        let mut aim_state = raw_aim_state();
+        if aim {
+            aim_state.next = next;
+            next = &mut aim_state as *mut _ as _;
+        }
+        let mut capsules_state = raw_capsules_state();
+        if capsules {
+            capsules_state.next = next;
+            next = &mut capsules_state as *mut _ as _;
+        }
+        let mut velocities = [HandJointVelocity {
+            velocity_flags: SpaceVelocityFlags::EMPTY,
+            linear_velocity: Default::default(),
+            angular_velocity: Default::default(),
+        }; 26];
+        let mut velocity_info = openxr::sys::HandJointVelocitiesEXT {
+            ty: openxr::sys::HandJointVelocitiesEXT::TYPE,
+            next,
+            joint_count: velocities.len() as u32,
+            joint_velocities: velocities.as_mut_ptr(),
+        };
+        let range_info = openxr::sys::HandJointsMotionRangeInfoEXT {
+            ty: openxr::sys::HandJointsMotionRangeInfoEXT::TYPE,
+            next: ptr::null(),
+            hand_joints_motion_range: options.motion_range.to_xr(),
+        };
+        let info_next = match motion_range {
+            true => &range_info as *const _ as _,
+            false => ptr::null(),
+        };
+        if options.velocities {
+            next = &mut velocity_info as *mut _ as _;
+        }
+        let locations = unsafe {
+            locate_hand_joints_chained(
+                &self.tracking.instance,
+                self.tracking.hand_tracker(side),
+                &self.input.stage,
+                self.frame_state.predicted_display_time,
+                info_next,
+                next,
+            )
+        }
+        .ok()
+        .flatten()?;
+        Some(LocatedHand {
+            joints: HandJoints {
+                inner: std::array::from_fn(|i| {
+                    HandJoint::new(&locations[i], options.velocities.then_some(&velocities[i]))
+                }),
+            },
+            aim: aim.then(|| HandAimState::from_raw(&aim_state)),
+            capsules: capsules.then(|| capsules_from_raw(&capsules_state)),
+        })
+    }
+}
+
+/// Locates the hand joints with `info_next` chained to the locate info and `next` chained to
+/// the joint locations, for extension structs like the motion range, aim or capsule state.
+/// Returns the joint locations if the hand is tracked
+///
+/// # Safety
+/// `info_next` and `next` have to be null or point to valid chains of input and output structs
+pub unsafe fn locate_hand_joints_chained(
+    instance: &openxr::Instance,
+    hand_tracker: &HandTracker,
+    base: &openxr::Space,
+    time: openxr::Time,
+    info_next: *const c_void,
+    next: *mut c_void,
+) -> Result<Option<[HandJointLocation; 26]>> {
+    let fp = instance
+        .exts()
+        .ext_hand_tracking
+        .ok_or(openxr::sys::Result::ERROR_EXTENSION_NOT_PRESENT)?;
+    let mut joints = [HandJointLocation {
+        location_flags: SpaceLocationFlags::EMPTY,
+        pose: openxr::Posef::IDENTITY,
+        radius: 0.0,
+    }; 26];
+    let mut locations = openxr::sys::HandJointLocationsEXT {
+        ty: openxr::sys::HandJointLocationsEXT::TYPE,
+        next,
+        is_active: openxr::sys::FALSE,
+        joint_count: joints.len() as u32,
+        joint_locations: joints.as_mut_ptr(),
+    };
+    let info = openxr::sys::HandJointsLocateInfoEXT {
+        ty: openxr::sys::HandJointsLocateInfoEXT::TYPE,
+        next: info_next,
+        base_space: base.as_raw(),
+        time,
+    };
+    cvt((fp.locate_hand_joints)(
+        hand_tracker.as_raw(),
+        &info,
+        &mut locations,
+    ))?;
+    Ok(bool::from(locations.is_active).then_some(joints))
 }
 
 impl Plugin for HandTrackingPlugin {
     fn build(&self, app: &mut App) {
+        app.init_resource::<HandMotionRanges>();
+        app.init_resource::<LocatedHands>();
         app.add_systems(
             PreUpdate,
-            (
-                update_hand_bones
-                    .run_if(|dh: Option<Res<DisableHandTracking>>| {
-                        !dh.is_some_and(|v| *v == DisableHandTracking::Both)
-                    })
-                    .run_if(xr_only()),
-                update_tracking_state_on_disable,
-            ),
+            (locate_hands, update_hand_bones).chain().run_if(xr_only()),
         );
     }
 }
 
-fn update_tracking_state_on_disable(
-    mut is_off: Local<bool>,
-    disabled_tracking: Option<Res<DisableHandTracking>>,
-    mut tracking_states: Query<&mut BoneTrackingStatus>,
-) {
-    if !*is_off
-        && disabled_tracking
-            .as_ref()
-            .is_some_and(|t| **t == DisableHandTracking::Both)
-    {
-        tracking_states
-            .par_iter_mut()
-            .for_each(|mut state| *state = BoneTrackingStatus::Emulated);
+/// The joints of both hands as located by [`locate_hands`] this frame, `None` while the hand
+/// isn't tracked or its tracking is disabled
+#[derive(Resource, Debug, Default)]
+pub struct LocatedHands {
+    pub left: Option<LocatedHand>,
+    pub right: Option<LocatedHand>,
+}
+
+impl LocatedHands {
+    pub fn get(&self, hand: Hand) -> Option<&LocatedHand> {
+        match hand {
+            Hand::Left => self.left.as_ref(),
+            Hand::Right => self.right.as_ref(),
+        }
     }
-    *is_off = disabled_tracking
-        .as_ref()
-        .is_some_and(|t| **t == DisableHandTracking::Both);
 }
 
-pub fn update_hand_bones(
+/// Locates both hands once per frame, with the velocities, aim and capsule states chained into
+/// the same locate call
+#[allow(clippy::too_many_arguments)]
+pub fn locate_hands(
     disabled_tracking: Option<Res<DisableHandTracking>>,
     hand_tracking: Option<Res<HandTrackingData>>,
+    motion_ranges: Res<HandMotionRanges>,
+    velocities: Option<Res<EnableHandJointVelocities>>,
+    aim_states: Option<ResMut<HandAimStates>>,
+    capsule_states: Option<ResMut<HandCapsuleStates>>,
     xr_input: Res<XrInput>,
     xr_frame_state: Res<XrFrameState>,
+    mut located: ResMut<LocatedHands>,
+) {
+    let hand_ref = hand_tracking
+        .as_ref()
+        .map(|h| h.get_ref(&xr_input, &xr_frame_state));
+    let [left, right] = [Hand::Left, Hand::Right].map(|hand| {
+        let disabled = match disabled_tracking.as_deref() {
+            Some(DisableHandTracking::Both) => true,
+            Some(DisableHandTracking::OnlyLeft) => hand == Hand::Left,
+            Some(DisableHandTracking::OnlyRight) => hand == Hand::Right,
+            None => false,
+        };
+        let options = HandLocateOptions {
+            motion_range: motion_ranges.get(hand),
+            velocities: velocities.is_some(),
+            aim: aim_states.is_some(),
+            capsules: capsule_states.is_some(),
+        };
+        match (&hand_ref, disabled) {
+            (Some(hand_ref), false) => hand_ref.locate(hand, options),
+            _ => None,
+        }
+    });
+    if let Some(mut capsule_states) = capsule_states {
+        capsule_states.left = left.as_ref().and_then(|d| d.capsules);
+        capsule_states.right = right.as_ref().and_then(|d| d.capsules);
+    }
+    if let Some(mut aim_states) = aim_states {
+        aim_states.left = left.as_ref().and_then(|d| d.aim).unwrap_or_default();
+        aim_states.right = right.as_ref().and_then(|d| d.aim).unwrap_or_default();
+    }
+    located.left = left;
+    located.right = right;
+}
+
+/// Writes the located joints to the bones of hands whose [`HandDataSource`] is
+/// [`HandDataSource::Tracked`], or that were located when there is no [`HandDataSources`].
+/// Bones of other hands are marked as emulated and written by the hand emulation
+#[allow(clippy::type_complexity)]
+pub fn update_hand_bones(
+    located: Res<LocatedHands>,
+    sources: Option<Res<HandDataSources>>,
+    mut tracking: TrackingStatusParams,
     mut bones: Query<(
+        Entity,
         &mut Transform,
         &Hand,
         &HandBone,
         &mut HandBoneRadius,
         &mut BoneTrackingStatus,
+        Option<&mut XrVelocity>,
+        Option<&mut XrTrackingStatus>,
+        Option<&mut Visibility>,
     )>,
 ) {
-    let hand_ref = match hand_tracking.as_ref() {
-        Some(h) => h.get_ref(&xr_input, &xr_frame_state),
-        None => {
-            warn!("No Handtracking data!");
-            return;
-        }
-    };
-    let left_hand_data = hand_ref.get_poses(Hand::Left);
-    let right_hand_data = hand_ref.get_poses(Hand::Right);
-    // if left_hand_data.is_none() || right_hand_data.is_none() {
-    //     error!("something is very wrong for hand_tracking!! doesn't have data for both hands!");
-    // }
-
-    bones
-        .par_iter_mut()
-        .for_each(|(mut transform, hand, bone, mut radius, mut status)| {
-            match (&hand, disabled_tracking.as_ref().map(|d| d.as_ref())) {
-                (Hand::Left, Some(DisableHandTracking::OnlyLeft)) => {
-                    *status = BoneTrackingStatus::Emulated;
-                    return;
-                }
-                (Hand::Right, Some(DisableHandTracking::OnlyRight)) => {
-                    *status = BoneTrackingStatus::Emulated;
-                    return;
-                }
-                _ => {}
-            }
-            let bone_data = match (hand, &left_hand_data, &right_hand_data) {
-                (Hand::Left, Some(data), _) => data.get_joint(*bone),
-                (Hand::Right, _, Some(data)) => data.get_joint(*bone),
-                (hand, left_data, right_data) => {
-                    *status = BoneTrackingStatus::Emulated;
-                    return;
+    for (
+        entity,
+        mut transform,
+        hand,
+        bone,
+        mut radius,
+        mut status,
+        velocity,
+        tracking_status,
+        visibility,
+    ) in &mut bones
+    {
+        let tracked = sources
+            .as_ref()
+            .map_or(true, |s| s.get(*hand) == HandDataSource::Tracked);
+        let bone_data = match located.get(*hand).filter(|_| tracked) {
+            Some(data) => data.joints.get_joint(*bone),
+            None => {
+                status.set_if_neq(BoneTrackingStatus::Emulated);
+                if let Some(mut velocity) = velocity {
+                    *velocity = XrVelocity::default();
                 }
-            };
-            if *status == BoneTrackingStatus::Emulated {
-                *status = BoneTrackingStatus::Tracked;
+                continue;
             }
-            radius.0 = bone_data.radius;
-            transform.translation = bone_data.position;
-            transform.rotation = bone_data.orientation;
-        });
+        };
+        status.set_if_neq(BoneTrackingStatus::Tracked);
+        tracking.update_status(
+            entity,
+            bone_data.tracking_status(),
+            tracking_status,
+            visibility,
+        );
+        radius.0 = bone_data.radius;
+        transform.translation = bone_data.position;
+        transform.rotation = bone_data.orientation;
+        if let Some(mut velocity) = velocity {
+            *velocity = bone_data.velocity();
+        }
+    }
 }
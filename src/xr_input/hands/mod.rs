@@ -14,7 +14,14 @@ use self::{
 use super::{trackers::OpenXRTracker, Hand};
 
 pub mod common;
+pub mod data_source;
 pub mod emulated;
+pub mod gestures;
+pub mod hand_aim;
+pub mod hand_capsules;
+pub mod hand_mesh;
+pub mod hand_pose;
+pub mod hand_rig;
 pub mod hand_tracking;
 
 pub struct HandPlugin;
@@ -68,6 +75,68 @@ pub enum BoneTrackingStatus {
     Tracked,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Finger {
+    Thumb,
+    Index,
+    Middle,
+    Ring,
+    Little,
+}
+impl Finger {
+    pub const fn get_all_fingers() -> [Finger; 5] {
+        [
+            Finger::Thumb,
+            Finger::Index,
+            Finger::Middle,
+            Finger::Ring,
+            Finger::Little,
+        ]
+    }
+    /// the bones of the finger from the metacarpal to the tip, the thumb has no intermediate
+    pub fn get_bones(&self) -> &'static [HandBone] {
+        match self {
+            Finger::Thumb => &[
+                HandBone::ThumbMetacarpal,
+                HandBone::ThumbProximal,
+                HandBone::ThumbDistal,
+                HandBone::ThumbTip,
+            ],
+            Finger::Index => &[
+                HandBone::IndexMetacarpal,
+                HandBone::IndexProximal,
+                HandBone::IndexIntermediate,
+                HandBone::IndexDistal,
+                HandBone::IndexTip,
+            ],
+            Finger::Middle => &[
+                HandBone::MiddleMetacarpal,
+                HandBone::MiddleProximal,
+                HandBone::MiddleIntermediate,
+                HandBone::MiddleDistal,
+                HandBone::MiddleTip,
+            ],
+            Finger::Ring => &[
+                HandBone::RingMetacarpal,
+                HandBone::RingProximal,
+                HandBone::RingIntermediate,
+                HandBone::RingDistal,
+                HandBone::RingTip,
+            ],
+            Finger::Little => &[
+                HandBone::LittleMetacarpal,
+                HandBone::LittleProximal,
+                HandBone::LittleIntermediate,
+                HandBone::LittleDistal,
+                HandBone::LittleTip,
+            ],
+        }
+    }
+    pub fn get_tip(&self) -> HandBone {
+        *self.get_bones().last().unwrap()
+    }
+}
+
 #[derive(Component, Debug, Clone, Copy)]
 pub enum HandBone {
     Palm,
@@ -0,0 +1,309 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use openxr as xr;
+
+use crate::{
+    resources::XrSession,
+    xr_init::{xr_only, XrCleanup},
+};
+
+use super::{
+    actions::XrActionSets, controllers::CONTROLLER_ACTION_SET, oculus_touch::subaction_path, Hand,
+};
+
+const HAPTIC_ACTION_SET: &str = CONTROLLER_ACTION_SET;
+const HAPTIC_ACTION: &str = "haptic_feedback";
+
+/// Plays [`HapticPulse`]s, [`PlayHapticPattern`]s and handles [`StopHaptics`]
+pub struct XrHapticsPlugin;
+
+impl Plugin for XrHapticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<HapticPulse>();
+        app.add_event::<StopHaptics>();
+        app.add_event::<PlayHapticPattern>();
+        app.init_resource::<ActiveHapticPatterns>();
+        app.add_systems(
+            PostUpdate,
+            (
+                stop_haptics,
+                start_haptic_patterns,
+                tick_haptic_patterns,
+                apply_haptic_pulses,
+            )
+                .chain()
+                .run_if(xr_only()),
+        );
+        app.add_systems(XrCleanup, clear_haptic_patterns);
+    }
+}
+
+/// A single vibration on one hand
+#[derive(Event, Clone, Copy, Debug, PartialEq)]
+pub struct HapticPulse {
+    pub hand: Hand,
+    /// 0.0 to 1.0
+    pub amplitude: f32,
+    /// in Hz, 0.0 lets the runtime pick the optimal frequency
+    pub frequency: f32,
+    /// a zero duration plays the shortest pulse the device supports
+    pub duration: Duration,
+}
+
+impl HapticPulse {
+    pub fn new(hand: Hand, amplitude: f32, frequency: f32, duration: Duration) -> Self {
+        Self {
+            hand,
+            amplitude,
+            frequency,
+            duration,
+        }
+    }
+    pub fn from_preset(hand: Hand, preset: HapticPreset) -> Self {
+        let (amplitude, frequency, duration) = preset.params();
+        Self::new(hand, amplitude, frequency, duration)
+    }
+    /// very short and light, for ui hover or scrolling through items
+    pub fn tick(hand: Hand) -> Self {
+        Self::from_preset(hand, HapticPreset::Tick)
+    }
+    /// short and strong, for collisions and button presses
+    pub fn bump(hand: Hand) -> Self {
+        Self::from_preset(hand, HapticPreset::Bump)
+    }
+    /// longer medium strength vibration, for errors or notifications
+    pub fn buzz(hand: Hand) -> Self {
+        Self::from_preset(hand, HapticPreset::Buzz)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum HapticPreset {
+    Tick,
+    Bump,
+    Buzz,
+}
+
+impl HapticPreset {
+    /// amplitude, frequency and duration of the preset
+    pub fn params(&self) -> (f32, f32, Duration) {
+        match self {
+            HapticPreset::Tick => (
+                0.2,
+                xr::sys::FREQUENCY_UNSPECIFIED,
+                Duration::from_millis(10),
+            ),
+            HapticPreset::Bump => (
+                0.8,
+                xr::sys::FREQUENCY_UNSPECIFIED,
+                Duration::from_millis(40),
+            ),
+            HapticPreset::Buzz => (0.5, 160.0, Duration::from_millis(250)),
+        }
+    }
+}
+
+/// Stops all vibrations and running patterns on a hand
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StopHaptics {
+    pub hand: Hand,
+}
+
+/// A pulse inside of a [`HapticPattern`], `offset` is relative to the start of the pattern
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TimedHapticPulse {
+    pub offset: Duration,
+    pub amplitude: f32,
+    pub frequency: f32,
+    pub duration: Duration,
+}
+
+/// A sequence of pulses played over time, multiple patterns can be layered into one
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HapticPattern {
+    pulses: Vec<TimedHapticPulse>,
+}
+
+impl HapticPattern {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// adds a pulse at `offset` from the start of the pattern
+    pub fn pulse(
+        mut self,
+        offset: Duration,
+        amplitude: f32,
+        frequency: f32,
+        duration: Duration,
+    ) -> Self {
+        self.pulses.push(TimedHapticPulse {
+            offset,
+            amplitude,
+            frequency,
+            duration,
+        });
+        self.pulses.sort_by_key(|p| p.offset);
+        self
+    }
+    pub fn preset(self, offset: Duration, preset: HapticPreset) -> Self {
+        let (amplitude, frequency, duration) = preset.params();
+        self.pulse(offset, amplitude, frequency, duration)
+    }
+    /// adds a pulse right after the last pulse of the pattern ended
+    pub fn then(self, gap: Duration, amplitude: f32, frequency: f32, duration: Duration) -> Self {
+        let offset = self.duration() + gap;
+        self.pulse(offset, amplitude, frequency, duration)
+    }
+    /// plays `other` at the same time as this pattern, starting at `offset`
+    pub fn layer(mut self, offset: Duration, other: &HapticPattern) -> Self {
+        self.pulses
+            .extend(other.pulses.iter().map(|p| TimedHapticPulse {
+                offset: p.offset + offset,
+                ..*p
+            }));
+        self.pulses.sort_by_key(|p| p.offset);
+        self
+    }
+    /// repeats the pattern `count` times with `gap` between the repetitions
+    pub fn repeat(self, count: u32, gap: Duration) -> Self {
+        let length = self.duration() + gap;
+        let mut out = HapticPattern::new();
+        for i in 0..count {
+            out = out.layer(length * i, &self);
+        }
+        out
+    }
+    /// time from the start of the pattern until the last pulse ends
+    pub fn duration(&self) -> Duration {
+        self.pulses
+            .iter()
+            .map(|p| p.offset + p.duration)
+            .max()
+            .unwrap_or_default()
+    }
+    pub fn pulses(&self) -> &[TimedHapticPulse] {
+        &self.pulses
+    }
+}
+
+#[derive(Event, Clone, Debug)]
+pub struct PlayHapticPattern {
+    pub hand: Hand,
+    pub pattern: HapticPattern,
+}
+
+struct PlayingHapticPattern {
+    hand: Hand,
+    pattern: HapticPattern,
+    elapsed: Duration,
+    next_pulse: usize,
+}
+
+#[derive(Resource, Default)]
+pub struct ActiveHapticPatterns {
+    playing: Vec<PlayingHapticPattern>,
+}
+
+impl ActiveHapticPatterns {
+    pub fn is_playing(&self, hand: Hand) -> bool {
+        self.playing.iter().any(|p| p.hand == hand)
+    }
+}
+
+fn clear_haptic_patterns(mut patterns: ResMut<ActiveHapticPatterns>) {
+    patterns.playing.clear();
+}
+
+fn stop_haptics(
+    mut events: EventReader<StopHaptics>,
+    mut patterns: ResMut<ActiveHapticPatterns>,
+    action_sets: Res<XrActionSets>,
+    session: Res<XrSession>,
+) {
+    for event in events.read() {
+        patterns.playing.retain(|p| p.hand != event.hand);
+        let action = match action_sets.get_action_haptic(HAPTIC_ACTION_SET, HAPTIC_ACTION) {
+            Ok(a) => a,
+            Err(err) => {
+                warn!("unable to get haptic action: {}", err);
+                continue;
+            }
+        };
+        if let Err(err) = action.stop_feedback(&session, subaction_path(event.hand)) {
+            warn!("unable to stop haptic feedback: {}", err);
+        }
+    }
+}
+
+fn start_haptic_patterns(
+    mut events: EventReader<PlayHapticPattern>,
+    mut patterns: ResMut<ActiveHapticPatterns>,
+) {
+    for event in events.read() {
+        patterns.playing.push(PlayingHapticPattern {
+            hand: event.hand,
+            pattern: event.pattern.clone(),
+            elapsed: Duration::ZERO,
+            next_pulse: 0,
+        });
+    }
+}
+
+fn tick_haptic_patterns(
+    time: Res<Time>,
+    mut patterns: ResMut<ActiveHapticPatterns>,
+    mut writer: EventWriter<HapticPulse>,
+) {
+    for playing in patterns.playing.iter_mut() {
+        for pulse in playing.pattern.pulses[playing.next_pulse..]
+            .iter()
+            .take_while(|p| p.offset <= playing.elapsed)
+        {
+            writer.send(HapticPulse {
+                hand: playing.hand,
+                amplitude: pulse.amplitude,
+                frequency: pulse.frequency,
+                duration: pulse.duration,
+            });
+            playing.next_pulse += 1;
+        }
+        playing.elapsed += time.delta();
+    }
+    patterns
+        .playing
+        .retain(|p| p.next_pulse < p.pattern.pulses.len());
+}
+
+fn apply_haptic_pulses(
+    mut events: EventReader<HapticPulse>,
+    action_sets: Res<XrActionSets>,
+    session: Res<XrSession>,
+) {
+    let action = match action_sets.get_action_haptic(HAPTIC_ACTION_SET, HAPTIC_ACTION) {
+        Ok(a) => a,
+        Err(err) => {
+            if !events.is_empty() {
+                warn!("unable to get haptic action: {}", err);
+            }
+            events.clear();
+            return;
+        }
+    };
+    for pulse in events.read() {
+        let duration = match pulse.duration.is_zero() {
+            true => xr::Duration::MIN_HAPTIC,
+            false => xr::Duration::from_nanos(pulse.duration.as_nanos() as i64),
+        };
+        if let Err(err) = action.apply_feedback(
+            &session,
+            subaction_path(pulse.hand),
+            &xr::HapticVibration::new()
+                .amplitude(pulse.amplitude.clamp(0.0, 1.0))
+                .frequency(pulse.frequency)
+                .duration(duration),
+        ) {
+            warn!("unable to apply haptic feedback: {}", err);
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use openxr as xr;
+
+use crate::resources::{XrInstance, XrSession};
+
+use super::Hand;
+
+pub const LEFT_HAND_USER_PATH: &str = "/user/hand/left";
+pub const RIGHT_HAND_USER_PATH: &str = "/user/hand/right";
+
+/// Interaction profiles known to this crate, everything else ends up as [`InteractionProfile::Unknown`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Reflect)]
+pub enum InteractionProfile {
+    /// No interaction profile is active for this user path, e.g. the controller is turned off
+    #[default]
+    None,
+    KhrSimple,
+    OculusTouch,
+    MetaTouchPro,
+    ValveIndex,
+    HtcVive,
+    HtcViveCosmos,
+    HtcViveFocus3,
+    MicrosoftMotion,
+    HpMixedReality,
+    SamsungOdyssey,
+    HuaweiController,
+    MagicLeap2,
+    Unknown,
+}
+
+impl InteractionProfile {
+    pub const fn all_known() -> [InteractionProfile; 12] {
+        [
+            InteractionProfile::KhrSimple,
+            InteractionProfile::OculusTouch,
+            InteractionProfile::MetaTouchPro,
+            InteractionProfile::ValveIndex,
+            InteractionProfile::HtcVive,
+            InteractionProfile::HtcViveCosmos,
+            InteractionProfile::HtcViveFocus3,
+            InteractionProfile::MicrosoftMotion,
+            InteractionProfile::HpMixedReality,
+            InteractionProfile::SamsungOdyssey,
+            InteractionProfile::HuaweiController,
+            InteractionProfile::MagicLeap2,
+        ]
+    }
+    pub fn path(&self) -> Option<&'static str> {
+        Some(match self {
+            InteractionProfile::None | InteractionProfile::Unknown => return None,
+            InteractionProfile::KhrSimple => "/interaction_profiles/khr/simple_controller",
+            InteractionProfile::OculusTouch => "/interaction_profiles/oculus/touch_controller",
+            InteractionProfile::MetaTouchPro => {
+                "/interaction_profiles/facebook/touch_controller_pro"
+            }
+            InteractionProfile::ValveIndex => "/interaction_profiles/valve/index_controller",
+            InteractionProfile::HtcVive => "/interaction_profiles/htc/vive_controller",
+            InteractionProfile::HtcViveCosmos => "/interaction_profiles/htc/vive_cosmos_controller",
+            InteractionProfile::HtcViveFocus3 => "/interaction_profiles/htc/vive_focus3_controller",
+            InteractionProfile::MicrosoftMotion => {
+                "/interaction_profiles/microsoft/motion_controller"
+            }
+            InteractionProfile::HpMixedReality => {
+                "/interaction_profiles/hp/mixed_reality_controller"
+            }
+            InteractionProfile::SamsungOdyssey => {
+                "/interaction_profiles/samsung/odyssey_controller"
+            }
+            InteractionProfile::HuaweiController => "/interaction_profiles/huawei/controller",
+            InteractionProfile::MagicLeap2 => "/interaction_profiles/ml/ml2_controller",
+        })
+    }
+    pub fn from_path(path: &str) -> Self {
+        if path.is_empty() {
+            return InteractionProfile::None;
+        }
+        Self::all_known()
+            .into_iter()
+            .find(|p| p.path() == Some(path))
+            .unwrap_or(InteractionProfile::Unknown)
+    }
+}
+
+/// The interaction profile currently bound to a top level user path
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CurrentInteractionProfile {
+    pub profile: InteractionProfile,
+    /// the raw interaction profile path reported by the runtime, empty if there is none
+    pub path: String,
+}
+
+/// Interaction profiles per top level user path, updated when the runtime sends
+/// `XrEventDataInteractionProfileChanged`
+#[derive(Resource, Clone, Debug, Default)]
+pub struct XrInteractionProfiles {
+    profiles: HashMap<&'static str, CurrentInteractionProfile>,
+}
+
+impl XrInteractionProfiles {
+    pub fn get(&self, user_path: &str) -> Option<&CurrentInteractionProfile> {
+        self.profiles.get(user_path)
+    }
+    pub fn hand(&self, hand: Hand) -> InteractionProfile {
+        self.get(hand_user_path(hand))
+            .map(|p| p.profile)
+            .unwrap_or_default()
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (&&'static str, &CurrentInteractionProfile)> {
+        self.profiles.iter()
+    }
+}
+
+#[derive(Event, Clone, Debug)]
+pub struct XrInteractionProfileChanged {
+    pub user_path: &'static str,
+    pub hand: Hand,
+    pub profile: InteractionProfile,
+    pub path: String,
+}
+
+pub fn hand_user_path(hand: Hand) -> &'static str {
+    match hand {
+        Hand::Left => LEFT_HAND_USER_PATH,
+        Hand::Right => RIGHT_HAND_USER_PATH,
+    }
+}
+
+pub(crate) fn update_interaction_profiles(
+    instance: &XrInstance,
+    session: &XrSession,
+    profiles: &mut XrInteractionProfiles,
+    writer: &mut EventWriter<XrInteractionProfileChanged>,
+) {
+    for (user_path, hand) in [
+        (LEFT_HAND_USER_PATH, Hand::Left),
+        (RIGHT_HAND_USER_PATH, Hand::Right),
+    ] {
+        let path = match instance
+            .string_to_path(user_path)
+            .and_then(|p| session.current_interaction_profile(p))
+        {
+            Ok(p) if p == xr::Path::NULL => String::new(),
+            Ok(p) => match instance.path_to_string(p) {
+                Ok(s) => s,
+                Err(err) => {
+                    warn!("unable to convert interaction profile path: {}", err);
+                    continue;
+                }
+            },
+            Err(err) => {
+                warn!(
+                    "unable to get interaction profile for {}: {}",
+                    user_path, err
+                );
+                continue;
+            }
+        };
+        let current = CurrentInteractionProfile {
+            profile: InteractionProfile::from_path(&path),
+            path,
+        };
+        if profiles.profiles.get(user_path) == Some(&current) {
+            continue;
+        }
+        info!(
+            "interaction profile for {} changed to {:?}",
+            user_path, current.profile
+        );
+        writer.send(XrInteractionProfileChanged {
+            user_path,
+            hand,
+            profile: current.profile,
+            path: current.path.clone(),
+        });
+        profiles.profiles.insert(user_path, current);
+    }
+}
+
+pub(crate) fn reset_interaction_profiles(mut profiles: ResMut<XrInteractionProfiles>) {
+    *profiles = XrInteractionProfiles::default();
+}
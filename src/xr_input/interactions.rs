@@ -1,10 +1,12 @@
-use std::f32::consts::PI;
+use std::f32::consts::{PI, SQRT_2};
 
 use bevy::log::{info, warn};
+use bevy::math::Affine3A;
 use bevy::prelude::{
-    Color, Component, Entity, Event, EventReader, EventWriter, Gizmos, GlobalTransform, Quat,
-    Query, Transform, Vec3, With, Without,
+    Capsule3d, Color, Component, Entity, Event, EventReader, EventWriter, GizmoPrimitive3d, Gizmos,
+    GlobalTransform, Mat3, Quat, Query, Transform, Vec3, With, Without,
 };
+use bevy::render::primitives::Aabb;
 
 use super::trackers::{AimPose, OpenXRTrackingRoot};
 
@@ -57,6 +59,370 @@ impl Default for XRSelection {
 #[derive(Component)]
 pub struct XRInteractable;
 
+/// The shape of an interactor or interactable used by the overlap and ray tests, in the local
+/// space of the entity and scaled with it. Entities without it get an unscaled sphere: 0.1 m for
+/// interactables and `0.1 * (√2 - 1)` m for interactors, so the two touch within √0.02 m of
+/// each other and rays hit the interactable within 0.1 m
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub enum InteractionVolume {
+    Sphere {
+        radius: f32,
+    },
+    Box {
+        half_size: Vec3,
+    },
+    /// a capsule along the local Y axis, `half_length` excludes the caps
+    Capsule {
+        radius: f32,
+        half_length: f32,
+    },
+    /// the [`Aabb`] of the entity's mesh, the default sphere until it is computed
+    Aabb,
+}
+
+impl Default for InteractionVolume {
+    fn default() -> Self {
+        InteractionVolume::Sphere {
+            radius: Self::INTERACTABLE_RADIUS,
+        }
+    }
+}
+
+impl InteractionVolume {
+    /// radius of the sphere of interactables without an [`InteractionVolume`]
+    const INTERACTABLE_RADIUS: f32 = 0.1;
+    /// radius of the sphere of interactors without an [`InteractionVolume`]
+    const INTERACTOR_RADIUS: f32 = 0.1 * (SQRT_2 - 1.0);
+
+    /// the volume of an entity in world space, an unscaled sphere of `default_radius` if it has
+    /// no [`InteractionVolume`]
+    fn world(
+        volume: Option<&InteractionVolume>,
+        default_radius: f32,
+        transform: &GlobalTransform,
+        aabb: Option<&Aabb>,
+    ) -> WorldVolume {
+        match volume {
+            Some(volume) => volume.to_world(transform, aabb),
+            None => WorldVolume {
+                core: Core::Point(transform.translation()),
+                radius: default_radius,
+            },
+        }
+    }
+
+    fn to_world(self, transform: &GlobalTransform, aabb: Option<&Aabb>) -> WorldVolume {
+        let affine = transform.affine();
+        let scale = Vec3::new(
+            affine.matrix3.x_axis.length(),
+            affine.matrix3.y_axis.length(),
+            affine.matrix3.z_axis.length(),
+        );
+        match self {
+            InteractionVolume::Sphere { radius } => WorldVolume {
+                core: Core::Point(transform.translation()),
+                radius: radius * scale.max_element(),
+            },
+            InteractionVolume::Box { half_size } => WorldVolume {
+                core: box_core(affine, scale, Vec3::ZERO, half_size),
+                radius: 0.0,
+            },
+            InteractionVolume::Capsule {
+                radius,
+                half_length,
+            } => WorldVolume {
+                core: Core::Segment(
+                    affine.transform_point3(Vec3::Y * half_length),
+                    affine.transform_point3(Vec3::NEG_Y * half_length),
+                ),
+                radius: radius * scale.x.max(scale.z),
+            },
+            InteractionVolume::Aabb => match aabb {
+                Some(aabb) => WorldVolume {
+                    core: box_core(affine, scale, aabb.center.into(), aabb.half_extents.into()),
+                    radius: 0.0,
+                },
+                None => InteractionVolume::default().to_world(transform, None),
+            },
+        }
+    }
+}
+
+fn box_core(affine: Affine3A, scale: Vec3, center: Vec3, half_size: Vec3) -> Core {
+    Core::Box {
+        center: affine.transform_point3(center),
+        axes: [
+            affine.matrix3.x_axis,
+            affine.matrix3.y_axis,
+            affine.matrix3.z_axis,
+        ]
+        .map(|axis| Vec3::from(axis).normalize_or_zero()),
+        half_size: half_size * scale,
+    }
+}
+
+/// the convex core of a volume in world space
+#[derive(Clone, Copy, Debug)]
+enum Core {
+    Point(Vec3),
+    Segment(Vec3, Vec3),
+    /// origin and normalized direction
+    Ray(Vec3, Vec3),
+    Box {
+        center: Vec3,
+        axes: [Vec3; 3],
+        half_size: Vec3,
+    },
+}
+
+impl Core {
+    fn closest_point(&self, point: Vec3) -> Vec3 {
+        match *self {
+            Core::Point(p) => p,
+            Core::Segment(a, b) => {
+                let ab = b - a;
+                let t = match ab.length_squared() > 0.0 {
+                    true => ((point - a).dot(ab) / ab.length_squared()).clamp(0.0, 1.0),
+                    false => 0.0,
+                };
+                a + ab * t
+            }
+            Core::Ray(origin, dir) => origin + dir * (point - origin).dot(dir).max(0.0),
+            Core::Box {
+                center,
+                axes,
+                half_size,
+            } => {
+                let local = point - center;
+                center
+                    + axes[0] * local.dot(axes[0]).clamp(-half_size.x, half_size.x)
+                    + axes[1] * local.dot(axes[1]).clamp(-half_size.y, half_size.y)
+                    + axes[2] * local.dot(axes[2]).clamp(-half_size.z, half_size.z)
+            }
+        }
+    }
+    /// closest distance between two cores, zero if they intersect
+    fn distance(&self, other: &Core) -> f32 {
+        match (*self, *other) {
+            (Core::Point(p), core) | (core, Core::Point(p)) => p.distance(core.closest_point(p)),
+            (
+                Core::Box {
+                    center,
+                    axes,
+                    half_size,
+                },
+                Core::Box {
+                    center: other_center,
+                    axes: other_axes,
+                    half_size: other_half_size,
+                },
+            ) => {
+                // boxes are closest at, or intersect along, an edge of one of them, unless one
+                // contains the other which the edges inside it find too
+                box_edges(center, axes, half_size)
+                    .into_iter()
+                    .map(|(a, b)| segment_box_distance(a, b - a, 1.0, other))
+                    .chain(
+                        box_edges(other_center, other_axes, other_half_size)
+                            .into_iter()
+                            .map(|(a, b)| segment_box_distance(a, b - a, 1.0, self)),
+                    )
+                    .fold(f32::INFINITY, f32::min)
+            }
+            (core, Core::Box { .. }) => {
+                let (origin, dir, max) = core.linear();
+                segment_box_distance(origin, dir, max, other)
+            }
+            (Core::Box { .. }, core) => {
+                let (origin, dir, max) = core.linear();
+                segment_box_distance(origin, dir, max, self)
+            }
+            (a, b) => {
+                let (p1, d1, max1) = a.linear();
+                let (p2, d2, max2) = b.linear();
+                let (on_a, on_b) = closest_segment_points(p1, d1, max1, p2, d2, max2);
+                on_a.distance(on_b)
+            }
+        }
+    }
+    /// origin, direction and parameter range of a point, segment or ray
+    fn linear(&self) -> (Vec3, Vec3, f32) {
+        match *self {
+            Core::Point(p) => (p, Vec3::ZERO, 0.0),
+            Core::Segment(a, b) => (a, b - a, 1.0),
+            Core::Ray(origin, dir) => (origin, dir, f32::INFINITY),
+            Core::Box { center, .. } => (center, Vec3::ZERO, 0.0),
+        }
+    }
+}
+
+/// the 12 edges of a box core
+fn box_edges(center: Vec3, axes: [Vec3; 3], half_size: Vec3) -> [(Vec3, Vec3); 12] {
+    let corner = |x: f32, y: f32, z: f32| {
+        center + axes[0] * half_size.x * x + axes[1] * half_size.y * y + axes[2] * half_size.z * z
+    };
+    let mut edges = [(Vec3::ZERO, Vec3::ZERO); 12];
+    for (i, (u, v)) in [(-1.0, -1.0), (-1.0, 1.0), (1.0, -1.0), (1.0, 1.0)]
+        .into_iter()
+        .enumerate()
+    {
+        edges[i * 3] = (corner(-1.0, u, v), corner(1.0, u, v));
+        edges[i * 3 + 1] = (corner(u, -1.0, v), corner(u, 1.0, v));
+        edges[i * 3 + 2] = (corner(u, v, -1.0), corner(u, v, 1.0));
+    }
+    edges
+}
+
+/// closest points of `p1 + d1 * s` and `p2 + d2 * t` with `s` in `0..=max1` and `t` in
+/// `0..=max2`, a ray has an infinite max
+fn closest_segment_points(
+    p1: Vec3,
+    d1: Vec3,
+    max1: f32,
+    p2: Vec3,
+    d2: Vec3,
+    max2: f32,
+) -> (Vec3, Vec3) {
+    const EPSILON: f32 = 1e-8;
+    let r = p1 - p2;
+    let a = d1.length_squared();
+    let e = d2.length_squared();
+    let f = d2.dot(r);
+    let (s, t) = if a <= EPSILON && e <= EPSILON {
+        (0.0, 0.0)
+    } else if a <= EPSILON {
+        (0.0, (f / e).clamp(0.0, max2))
+    } else {
+        let c = d1.dot(r);
+        if e <= EPSILON {
+            ((-c / a).clamp(0.0, max1), 0.0)
+        } else {
+            let b = d1.dot(d2);
+            let denom = a * e - b * b;
+            // parallel lines, any point of the first one works
+            let s = match denom > EPSILON {
+                true => ((b * f - c * e) / denom).clamp(0.0, max1),
+                false => 0.0,
+            };
+            let t = (b * s + f) / e;
+            if t < 0.0 {
+                ((-c / a).clamp(0.0, max1), 0.0)
+            } else if t > max2 {
+                (((b * max2 - c) / a).clamp(0.0, max1), max2)
+            } else {
+                (s, t)
+            }
+        }
+    };
+    (p1 + d1 * s, p2 + d2 * t)
+}
+
+/// slab test of `origin + dir * t` with `t` in `0..=max` against a box core
+fn segment_hits_box(
+    origin: Vec3,
+    dir: Vec3,
+    max: f32,
+    center: Vec3,
+    axes: [Vec3; 3],
+    half_size: Vec3,
+) -> bool {
+    let local = origin - center;
+    let (mut t_min, mut t_max) = (0.0f32, max);
+    for (i, axis) in axes.into_iter().enumerate() {
+        let o = local.dot(axis);
+        let d = dir.dot(axis);
+        if d.abs() <= f32::EPSILON {
+            if o.abs() > half_size[i] {
+                return false;
+            }
+            continue;
+        }
+        let t0 = (-half_size[i] - o) / d;
+        let t1 = (half_size[i] - o) / d;
+        t_min = t_min.max(t0.min(t1));
+        t_max = t_max.min(t0.max(t1));
+        if t_min > t_max {
+            return false;
+        }
+    }
+    true
+}
+
+/// closest distance between `origin + dir * t` with `t` in `0..=max` and a box core. Without an
+/// intersection the closest point is an end point or lies on an edge of the box
+fn segment_box_distance(origin: Vec3, dir: Vec3, max: f32, core: &Core) -> f32 {
+    let Core::Box {
+        center,
+        axes,
+        half_size,
+    } = *core
+    else {
+        return f32::INFINITY;
+    };
+    if segment_hits_box(origin, dir, max, center, axes, half_size) {
+        return 0.0;
+    }
+    let mut distance = origin.distance(core.closest_point(origin));
+    if max.is_finite() {
+        let end = origin + dir * max;
+        distance = distance.min(end.distance(core.closest_point(end)));
+    }
+    for (a, b) in box_edges(center, axes, half_size) {
+        let (on_segment, on_edge) = closest_segment_points(origin, dir, max, a, b - a, 1.0);
+        distance = distance.min(on_segment.distance(on_edge));
+    }
+    distance
+}
+
+/// an [`InteractionVolume`] in world space, the core grown by the radius
+#[derive(Clone, Copy, Debug)]
+struct WorldVolume {
+    core: Core,
+    radius: f32,
+}
+
+impl WorldVolume {
+    const EPSILON: f32 = 0.0001;
+
+    fn overlaps(&self, other: &WorldVolume) -> bool {
+        self.core.distance(&other.core) <= self.radius + other.radius + Self::EPSILON
+    }
+    fn intersects_ray(&self, origin: Vec3, dir: Vec3) -> bool {
+        Core::Ray(origin, dir).distance(&self.core) <= self.radius + Self::EPSILON
+    }
+    fn draw(&self, gizmos: &mut Gizmos, color: Color) {
+        match self.core {
+            Core::Point(p) => {
+                gizmos.sphere(p, Quat::IDENTITY, self.radius, color);
+            }
+            Core::Segment(a, b) => {
+                gizmos.primitive_3d(
+                    Capsule3d {
+                        radius: self.radius,
+                        half_length: a.distance(b) * 0.5,
+                    },
+                    (a + b) * 0.5,
+                    Quat::from_rotation_arc(Vec3::Y, (a - b).try_normalize().unwrap_or(Vec3::Y)),
+                    color,
+                );
+            }
+            Core::Ray(origin, dir) => gizmos.ray(origin, dir, color),
+            Core::Box {
+                center,
+                axes,
+                half_size,
+            } => {
+                let transform = Transform {
+                    translation: center,
+                    rotation: Quat::from_mat3(&Mat3::from_cols(axes[0], axes[1], axes[2])),
+                    scale: half_size * 2.0,
+                };
+                gizmos.cuboid(transform, color);
+            }
+        }
+    }
+}
+
 pub fn draw_socket_gizmos(
     mut gizmos: Gizmos,
     interactor_query: Query<(
@@ -64,23 +430,36 @@ pub fn draw_socket_gizmos(
         &XRInteractorState,
         Entity,
         &XRSocketInteractor,
+        Option<&InteractionVolume>,
+        Option<&Aabb>,
     )>,
 ) {
-    for (global, state, _entity, _socket) in interactor_query.iter() {
-        let mut transform = global.compute_transform().clone();
-        transform.scale = Vec3::splat(0.1);
+    for (global, state, _entity, _socket, volume, aabb) in interactor_query.iter() {
         let color = match state {
             XRInteractorState::Idle => Color::BLUE,
             XRInteractorState::Selecting => Color::PURPLE,
         };
-        gizmos.cuboid(transform, color)
+        match volume {
+            Some(volume) => volume.to_world(global, aabb).draw(&mut gizmos, color),
+            None => {
+                let mut transform = global.compute_transform().clone();
+                transform.scale = Vec3::splat(0.1);
+                gizmos.cuboid(transform, color)
+            }
+        }
     }
 }
 
+#[allow(clippy::type_complexity)]
 pub fn draw_interaction_gizmos(
     mut gizmos: Gizmos,
     interactable_query: Query<
-        (&GlobalTransform, &XRInteractableState),
+        (
+            &GlobalTransform,
+            &XRInteractableState,
+            Option<&InteractionVolume>,
+            Option<&Aabb>,
+        ),
         (With<XRInteractable>, Without<XRDirectInteractor>),
     >,
     interactor_query: Query<
@@ -90,6 +469,8 @@ pub fn draw_interaction_gizmos(
             Option<&XRDirectInteractor>,
             Option<&XRRayInteractor>,
             Option<&AimPose>,
+            Option<&InteractionVolume>,
+            Option<&Aabb>,
         ),
         Without<XRInteractable>,
     >,
@@ -99,21 +480,36 @@ pub fn draw_interaction_gizmos(
         warn!("no or more than one tracking root");
         return;
     };
-    for (global_transform, interactable_state) in interactable_query.iter() {
-        let transform = global_transform.compute_transform();
+    for (global_transform, interactable_state, volume, aabb) in interactable_query.iter() {
         let color = match interactable_state {
             XRInteractableState::Idle => Color::RED,
             XRInteractableState::Hover => Color::YELLOW,
             XRInteractableState::Select => Color::GREEN,
         };
-        gizmos.sphere(transform.translation, transform.rotation, 0.1, color);
+        InteractionVolume::world(
+            volume,
+            InteractionVolume::INTERACTABLE_RADIUS,
+            global_transform,
+            aabb,
+        )
+        .draw(&mut gizmos, color);
     }
 
-    for (interactor_global_transform, interactor_state, direct, ray, aim) in interactor_query.iter()
+    for (interactor_global_transform, interactor_state, direct, ray, aim, volume, aabb) in
+        interactor_query.iter()
     {
         let transform = interactor_global_transform.compute_transform();
-        match direct {
-            Some(_) => {
+        match (direct, volume) {
+            (Some(_), Some(volume)) => {
+                let color = match interactor_state {
+                    XRInteractorState::Idle => Color::BLUE,
+                    XRInteractorState::Selecting => Color::PURPLE,
+                };
+                volume
+                    .to_world(interactor_global_transform, aabb)
+                    .draw(&mut gizmos, color);
+            }
+            (Some(_), None) => {
                 let mut local = transform.clone();
                 local.scale = Vec3::splat(0.1);
                 let quat = Quat::from_euler(
@@ -129,7 +525,7 @@ pub fn draw_interaction_gizmos(
                 };
                 gizmos.cuboid(local, color);
             }
-            None => (),
+            (None, _) => (),
         }
         match ray {
             Some(_) => match aim {
@@ -158,9 +554,16 @@ pub struct InteractionEvent {
     pub interactable_state: XRInteractableState,
 }
 
+#[allow(clippy::type_complexity)]
 pub fn socket_interactions(
     interactable_query: Query<
-        (&GlobalTransform, &mut XRInteractableState, Entity),
+        (
+            &GlobalTransform,
+            &mut XRInteractableState,
+            Entity,
+            Option<&InteractionVolume>,
+            Option<&Aabb>,
+        ),
         (With<XRInteractable>, Without<XRSocketInteractor>),
     >,
     interactor_query: Query<
@@ -169,6 +572,8 @@ pub fn socket_interactions(
             &XRInteractorState,
             Entity,
             &XRSocketInteractor,
+            Option<&InteractionVolume>,
+            Option<&Aabb>,
         ),
         Without<XRInteractable>,
     >,
@@ -180,18 +585,20 @@ pub fn socket_interactions(
             let interactor_global_transform = socket.0;
             let xr_interactable_global_transform = interactable.0;
             let interactor_state = socket.1;
-            //check for sphere overlaps
-            let size = 0.1;
-            if interactor_global_transform
-                .compute_transform()
-                .translation
-                .distance_squared(
-                    xr_interactable_global_transform
-                        .compute_transform()
-                        .translation,
-                )
-                < (size * size) * 2.0
-            {
+            //check for volume overlaps
+            let socket_volume = InteractionVolume::world(
+                socket.4,
+                InteractionVolume::INTERACTOR_RADIUS,
+                interactor_global_transform,
+                socket.5,
+            );
+            let interactable_volume = InteractionVolume::world(
+                interactable.3,
+                InteractionVolume::INTERACTABLE_RADIUS,
+                xr_interactable_global_transform,
+                interactable.4,
+            );
+            if socket_volume.overlaps(&interactable_volume) {
                 //check for selections first
                 match interactor_state {
                     XRInteractorState::Idle => {
@@ -216,9 +623,15 @@ pub fn socket_interactions(
     }
 }
 
+#[allow(clippy::type_complexity)]
 pub fn interactions(
     interactable_query: Query<
-        (&GlobalTransform, Entity),
+        (
+            &GlobalTransform,
+            Entity,
+            Option<&InteractionVolume>,
+            Option<&Aabb>,
+        ),
         (With<XRInteractable>, Without<XRDirectInteractor>),
     >,
     interactor_query: Query<
@@ -229,30 +642,48 @@ pub fn interactions(
             Option<&XRDirectInteractor>,
             Option<&XRRayInteractor>,
             Option<&AimPose>,
+            Option<&InteractionVolume>,
+            Option<&Aabb>,
         ),
         Without<XRInteractable>,
     >,
     tracking_root_query: Query<&mut Transform, With<OpenXRTrackingRoot>>,
     mut writer: EventWriter<InteractionEvent>,
 ) {
-    for (xr_interactable_global_transform, interactable_entity) in interactable_query.iter() {
-        for (interactor_global_transform, interactor_state, interactor_entity, direct, ray, aim) in
-            interactor_query.iter()
+    for (
+        xr_interactable_global_transform,
+        interactable_entity,
+        interactable_volume,
+        interactable_aabb,
+    ) in interactable_query.iter()
+    {
+        let interactable_volume = InteractionVolume::world(
+            interactable_volume,
+            InteractionVolume::INTERACTABLE_RADIUS,
+            xr_interactable_global_transform,
+            interactable_aabb,
+        );
+        for (
+            interactor_global_transform,
+            interactor_state,
+            interactor_entity,
+            direct,
+            ray,
+            aim,
+            interactor_volume,
+            interactor_aabb,
+        ) in interactor_query.iter()
         {
             match direct {
                 Some(_) => {
-                    //check for sphere overlaps
-                    let size = 0.1;
-                    if interactor_global_transform
-                        .compute_transform()
-                        .translation
-                        .distance_squared(
-                            xr_interactable_global_transform
-                                .compute_transform()
-                                .translation,
-                        )
-                        < (size * size) * 2.0
-                    {
+                    //check for volume overlaps
+                    let interactor_volume = InteractionVolume::world(
+                        interactor_volume,
+                        InteractionVolume::INTERACTOR_RADIUS,
+                        interactor_global_transform,
+                        interactor_aabb,
+                    );
+                    if interactor_volume.overlaps(&interactable_volume) {
                         //check for selections first
                         match interactor_state {
                             XRInteractorState::Idle => {
@@ -278,10 +709,7 @@ pub fn interactions(
             }
             match ray {
                 Some(_) => {
-                    //check for ray-sphere intersection
-                    let sphere_transform = xr_interactable_global_transform.compute_transform();
-                    let center = sphere_transform.translation;
-                    let radius: f32 = 0.1;
+                    //check for ray-volume intersection
                     //I hate this but the aim pose needs the root for now
                     let root = tracking_root_query.get_single().unwrap();
                     match aim {
@@ -290,12 +718,9 @@ pub fn interactions(
                                 root.translation + root.rotation.mul_vec3(aim.0.translation);
                             let ray_dir = root.rotation.mul_vec3(*aim.0.forward());
 
-                            if ray_sphere_intersection(
-                                center,
-                                radius,
-                                ray_origin,
-                                ray_dir.normalize_or_zero(),
-                            ) {
+                            if interactable_volume
+                                .intersects_ray(ray_origin, ray_dir.normalize_or_zero())
+                            {
                                 //check for selections first
                                 match interactor_state {
                                     XRInteractorState::Idle => {
@@ -362,23 +787,3 @@ pub fn update_interactable_states(
         }
     }
 }
-
-fn ray_sphere_intersection(center: Vec3, radius: f32, ray_origin: Vec3, ray_dir: Vec3) -> bool {
-    let l = center - ray_origin;
-    let adj = l.dot(ray_dir);
-    let d2 = l.dot(l) - (adj * adj);
-    let radius2 = radius * radius;
-    if d2 > radius2 {
-        return false;
-    }
-    let thc = (radius2 - d2).sqrt();
-    let t0 = adj - thc;
-    let t1 = adj + thc;
-
-    if t0 < 0.0 && t1 < 0.0 {
-        return false;
-    }
-
-    // let distance = if t0 < t1 { t0 } else { t1 };
-    return true;
-}
@@ -0,0 +1,186 @@
+use std::sync::Arc;
+
+use bevy::math::Affine3A;
+use bevy::pbr::RenderMeshInstances;
+use bevy::prelude::*;
+use bevy::render::extract_resource::{ExtractResource, ExtractResourcePlugin};
+use bevy::render::view::ExtractedView;
+use bevy::render::{Extract, ExtractSchedule, Render, RenderApp, RenderSet};
+use openxr::{AnyGraphics, Posef, Session, Space};
+
+use crate::input::XrInput;
+use crate::resources::{XrFrameState, XrSession};
+use crate::xr_init::{xr_only, XrCleanup, XrPostSetup};
+
+use super::actions::XrActionSets;
+use super::controllers::{post_action_setup_xr_controllers, Handed, CONTROLLER_ACTION_SET};
+use super::oculus_touch::subaction_path;
+use super::trackers::{verify_quat, OpenXRTrackingRoot, XrTrackingStatus};
+use super::xr_camera::{xr_camera_head_sync_render_world, XrCamera};
+use super::{Hand, QuatConv, Vec3Conv};
+
+/// Re-locates the space of tracked entities with a [`LateLatch`] in the render world, right
+/// before rendering, like the eye cameras
+pub struct LateLatchPlugin;
+
+impl Plugin for LateLatchPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(ExtractResourcePlugin::<LateLatchSpaces>::default());
+        app.add_systems(
+            XrPostSetup,
+            setup_late_latch_spaces.after(post_action_setup_xr_controllers),
+        );
+        app.add_systems(XrCleanup, cleanup_late_latch_spaces);
+        let render_app = app.sub_app_mut(RenderApp);
+        render_app.add_systems(ExtractSchedule, extract_late_latches);
+        render_app.add_systems(
+            Render,
+            late_latch_render_world
+                .after(xr_camera_head_sync_render_world)
+                .run_if(xr_only())
+                .in_set(RenderSet::PrepareAssets),
+        );
+    }
+}
+
+/// Opt-in late latching of a tracked entity, the entity and all of its descendants get moved
+/// by the difference between the pose used in `Update` and the pose located right before
+/// rendering. Only the rendered meshes and views are moved, the main world is left untouched.
+///
+/// The located pose replaces the pose of the nearest ancestor that is a child of the
+/// [`OpenXRTrackingRoot`], so a latch on a descendant of a tracked entity, like a held item,
+/// keeps its offset to that entity.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LateLatch {
+    /// the grip pose of a controller
+    Grip(Hand),
+    /// the aim pose of a controller
+    Aim(Hand),
+    /// the VIEW space, for entities following the users head
+    Head,
+}
+
+/// Separate spaces for the render world, so the main world spaces are never shared
+#[derive(Resource, Clone, ExtractResource)]
+pub struct LateLatchSpaces {
+    grip: Handed<Arc<Space>>,
+    aim: Handed<Arc<Space>>,
+}
+
+fn setup_late_latch_spaces(
+    mut commands: Commands,
+    action_sets: Res<XrActionSets>,
+    session: Res<XrSession>,
+) {
+    let s = Session::<AnyGraphics>::clone(&session);
+    let create = |action: &'static str| {
+        let action = action_sets
+            .get_action_posef(CONTROLLER_ACTION_SET, action)
+            .unwrap();
+        Handed {
+            left: Arc::new(
+                action
+                    .create_space(s.clone(), subaction_path(Hand::Left), Posef::IDENTITY)
+                    .unwrap(),
+            ),
+            right: Arc::new(
+                action
+                    .create_space(s.clone(), subaction_path(Hand::Right), Posef::IDENTITY)
+                    .unwrap(),
+            ),
+        }
+    };
+    commands.insert_resource(LateLatchSpaces {
+        grip: create("grip_pose"),
+        aim: create("aim_pose"),
+    });
+}
+
+fn cleanup_late_latch_spaces(mut commands: Commands) {
+    commands.remove_resource::<LateLatchSpaces>();
+}
+
+#[derive(Component)]
+struct ExtractedLateLatch {
+    latch: LateLatch,
+    root: GlobalTransform,
+    /// pose of the tracked ancestor relative to the tracking root, as used by the main world
+    pose: Transform,
+    /// the latched entity and all of its descendants
+    entities: Vec<Entity>,
+}
+
+fn extract_late_latches(
+    mut commands: Commands,
+    latch_query: Extract<Query<(Entity, &LateLatch)>>,
+    transform_query: Extract<Query<&GlobalTransform>>,
+    parent_query: Extract<Query<&Parent>>,
+    children_query: Extract<Query<&Children>>,
+    root_query: Extract<Query<(Entity, &GlobalTransform), With<OpenXRTrackingRoot>>>,
+) {
+    let (root_entity, root) = match root_query.get_single() {
+        Ok((entity, root)) => (entity, *root),
+        Err(_) => return,
+    };
+    for (entity, latch) in &latch_query {
+        // the entity itself when it isn't below the tracking root
+        let tracked = parent_query
+            .iter_ancestors(entity)
+            .zip(std::iter::once(entity).chain(parent_query.iter_ancestors(entity)))
+            .find(|(parent, _)| *parent == root_entity)
+            .map_or(entity, |(_, tracked)| tracked);
+        let pose = match transform_query.get(tracked) {
+            Ok(transform) => transform.reparented_to(&root),
+            Err(_) => continue,
+        };
+        let entities = std::iter::once(entity)
+            .chain(children_query.iter_descendants(entity))
+            .collect();
+        commands.get_or_spawn(entity).insert(ExtractedLateLatch {
+            latch: *latch,
+            root,
+            pose,
+            entities,
+        });
+    }
+}
+
+fn late_latch_render_world(
+    latch_query: Query<&ExtractedLateLatch>,
+    mut view_query: Query<&mut ExtractedView, Without<XrCamera>>,
+    mut mesh_instances: ResMut<RenderMeshInstances>,
+    spaces: Option<Res<LateLatchSpaces>>,
+    xr_input: Res<XrInput>,
+    frame_state: Res<XrFrameState>,
+) {
+    for latch in &latch_query {
+        let space = match (latch.latch, spaces.as_ref()) {
+            (LateLatch::Head, _) => &xr_input.head,
+            (LateLatch::Grip(hand), Some(spaces)) => spaces.grip.get(hand),
+            (LateLatch::Aim(hand), Some(spaces)) => spaces.aim.get(hand),
+            (_, None) => continue,
+        };
+        let location = match space.locate(&xr_input.stage, frame_state.predicted_display_time) {
+            Ok(location) => location,
+            Err(_) => continue,
+        };
+        if !XrTrackingStatus::from_flags(location.location_flags).is_valid() {
+            continue;
+        }
+        let pose = Transform::from_translation(location.pose.position.to_vec3())
+            .with_rotation(verify_quat(location.pose.orientation.to_quat()))
+            .with_scale(latch.pose.scale);
+        let root = latch.root.affine();
+        let correction =
+            root * pose.compute_affine() * latch.pose.compute_affine().inverse() * root.inverse();
+        for entity in &latch.entities {
+            if let Some(instance) = mesh_instances.get_mut(entity) {
+                let transform = correction * Affine3A::from(&instance.transforms.transform);
+                instance.transforms.transform = (&transform).into();
+            }
+            if let Ok(mut view) = view_query.get_mut(*entity) {
+                view.transform = GlobalTransform::from(correction * view.transform.affine());
+            }
+        }
+    }
+}
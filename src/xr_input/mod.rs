@@ -1,17 +1,23 @@
 pub mod actions;
+pub mod bevy_input;
 pub mod controllers;
 pub mod debug_gizmos;
+pub mod eye_gaze;
+pub mod facial_tracking;
 pub mod hand_poses;
 pub mod hands;
+pub mod haptics;
+pub mod interaction_profiles;
 pub mod interactions;
+pub mod late_latch;
 pub mod oculus_touch;
 pub mod prototype_locomotion;
 pub mod trackers;
+pub mod vive_trackers;
 pub mod xr_camera;
 
 use crate::resources::{XrInstance, XrSession};
 use crate::xr_init::{xr_only, XrCleanup, XrPostSetup, XrPreSetup, XrSetup};
-use crate::xr_input::oculus_touch::setup_oculus_controller;
 use crate::xr_input::xr_camera::{xr_camera_head_sync, Eye, XRProjection, XrCameraBundle};
 use crate::{locate_views, xr_wait_frame};
 use bevy::app::{App, PostUpdate, Startup};
@@ -29,20 +35,27 @@ use bevy::render::view::{update_frusta, VisibilitySystems};
 use bevy::transform::TransformSystem;
 use bevy::utils::HashMap;
 use openxr::Binding;
+use serde::{Deserialize, Serialize};
 
-use self::actions::{setup_oxr_actions, XrActionsPlugin};
-use self::oculus_touch::{
-    init_subaction_path, post_action_setup_oculus_controller, ActionSets, OculusController,
+use self::actions::{setup_oxr_actions, sync_actions, XrActionsPlugin};
+use self::controllers::{
+    cleanup_xr_controllers, insert_xr_controller_components, post_action_setup_xr_controllers,
+    setup_xr_controllers, update_xr_controller_components,
 };
+use self::interaction_profiles::reset_interaction_profiles;
+use self::oculus_touch::init_subaction_path;
 use self::trackers::{
-    adopt_open_xr_trackers, update_open_xr_controllers, OpenXRLeftEye, OpenXRRightEye,
-    OpenXRTrackingRoot,
+    adopt_open_xr_trackers, spawn_open_xr_hmd, update_open_xr_controllers, update_open_xr_hmd,
+    OpenXRLeftEye, OpenXRRightEye, OpenXRTrackingRoot, TrackingLossPolicy, TrackingLost,
+    TrackingRegained,
 };
 use self::xr_camera::{/* GlobalTransformExtract, TransformExtract, */ XrCamera};
 
 #[derive(Copy, Clone)]
 pub struct XrInputPlugin;
-#[derive(Clone, Copy, Debug, Ord, PartialOrd, Eq, PartialEq, Component)]
+#[derive(
+    Clone, Copy, Debug, Ord, PartialOrd, Eq, PartialEq, Hash, Component, Serialize, Deserialize,
+)]
 pub enum Hand {
     Left,
     Right,
@@ -50,24 +63,35 @@ pub enum Hand {
 
 impl Plugin for XrInputPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(XrPostSetup, post_action_setup_oculus_controller);
-        app.add_systems(XrSetup, setup_oculus_controller);
-        app.add_systems(XrCleanup, cleanup_oculus_controller);
+        app.init_resource::<TrackingLossPolicy>();
+        app.add_event::<TrackingLost>();
+        app.add_event::<TrackingRegained>();
+        app.add_systems(XrPostSetup, post_action_setup_xr_controllers);
+        app.add_systems(XrSetup, setup_xr_controllers);
+        app.add_systems(XrCleanup, cleanup_xr_controllers);
         //adopt any new trackers
         app.add_systems(PreUpdate, adopt_open_xr_trackers.run_if(xr_only()));
         // app.add_systems(PreUpdate, action_set_system.run_if(xr_only()));
         //update controller trackers
         app.add_systems(Update, update_open_xr_controllers.run_if(xr_only()));
+        app.add_systems(Update, update_open_xr_hmd.run_if(xr_only()));
+        app.add_systems(
+            PreUpdate,
+            (
+                insert_xr_controller_components,
+                update_xr_controller_components.after(sync_actions),
+            )
+                .chain()
+                .run_if(xr_only()),
+        );
         app.add_systems(XrPreSetup, init_subaction_path);
         app.add_systems(XrSetup, setup_xr_root);
+        app.add_systems(XrSetup, spawn_open_xr_hmd);
         app.add_systems(XrCleanup, cleanup_xr_root);
+        app.add_systems(XrCleanup, reset_interaction_profiles);
     }
 }
 
-fn cleanup_oculus_controller(mut commands: Commands) {
-    commands.remove_resource::<OculusController>();
-}
-
 fn cleanup_xr_root(
     mut commands: Commands,
     tracking_root_query: Query<Entity, With<OpenXRTrackingRoot>>,
@@ -1,8 +1,9 @@
 use crate::input::XrInput;
 use crate::resources::{XrInstance, XrSession};
+use crate::xr_init::{XrCleanup, XrPostSetup, XrSetup};
 use crate::xr_input::controllers::Handed;
 use crate::xr_input::Hand;
-use bevy::prelude::{default, Commands, Res, ResMut, Resource};
+use bevy::prelude::{default, App, Commands, Plugin, Res, ResMut, Resource};
 use openxr::{
     ActionSet, AnyGraphics, FrameState, Instance, Path, Posef, Session, Space, SpaceLocation,
     SpaceVelocity,
@@ -12,6 +13,25 @@ use std::sync::OnceLock;
 
 use super::actions::{ActionHandednes, ActionType, SetupActionSets, XrActionSets, XrBinding};
 
+/// Registers the `oculus_input` action set and the [`OculusController`] resource.
+///
+/// Superseded by [`XrControllers`](super::controllers::XrControllers), which the
+/// `XrInputPlugin` always registers and which binds Touch controllers too. Only add this plugin
+/// if you still read [`OculusController`], every Touch input is then bound in both action sets
+pub struct OculusControllerPlugin;
+
+impl Plugin for OculusControllerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(XrPostSetup, post_action_setup_oculus_controller);
+        app.add_systems(XrSetup, setup_oculus_controller);
+        app.add_systems(XrCleanup, cleanup_oculus_controller);
+    }
+}
+
+fn cleanup_oculus_controller(mut commands: Commands) {
+    commands.remove_resource::<OculusController>();
+}
+
 pub fn post_action_setup_oculus_controller(
     action_sets: Res<XrActionSets>,
     mut controller: ResMut<OculusController>,
@@ -340,7 +360,7 @@ impl OculusControllerRef<'_> {
     }
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Default)]
 pub struct Thumbstick {
     pub x: f32,
     pub y: f32,
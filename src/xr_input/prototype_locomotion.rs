@@ -11,7 +11,7 @@ use crate::{
 };
 
 use super::{
-    actions::XrActionSets, oculus_touch::OculusController, trackers::OpenXRTrackingRoot, Hand,
+    actions::XrActionSets, controllers::XrControllers, trackers::OpenXRTrackingRoot, Hand,
     QuatConv, Vec3Conv,
 };
 
@@ -60,7 +60,7 @@ impl Default for PrototypeLocomotionConfig {
 pub fn proto_locomotion(
     time: Res<Time>,
     mut tracking_root_query: Query<&mut Transform, With<OpenXRTrackingRoot>>,
-    oculus_controller: Res<OculusController>,
+    controllers: Res<XrControllers>,
     frame_state: Res<XrFrameState>,
     xr_input: Res<XrInput>,
     session: Res<XrSession>,
@@ -77,7 +77,7 @@ pub fn proto_locomotion(
         }
     };
     //get controller
-    let controller = oculus_controller.get_ref(&session, &frame_state, &xr_input, &action_sets);
+    let controller = controllers.get_ref(&session, &frame_state, &xr_input, &action_sets);
     let root = tracking_root_query.get_single_mut();
     match root {
         Ok(mut position) => {
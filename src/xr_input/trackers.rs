@@ -1,16 +1,21 @@
+use bevy::core::Name;
+use bevy::ecs::system::SystemParam;
 use bevy::hierarchy::Parent;
 use bevy::log::{debug, info};
 use bevy::math::Quat;
 use bevy::prelude::{
-    Added, BuildChildren, Commands, Component, Entity, Query, Res, Transform, Vec3, With, Without,
+    Added, BuildChildren, Commands, Component, DetectChangesMut, Entity, Event, EventWriter,
+    GlobalTransform, Has, Mut, Or, Query, Res, Resource, SpatialBundle, Transform, Vec3,
+    Visibility, With, Without,
 };
+use openxr::{SpaceLocation, SpaceLocationFlags, SpaceVelocity, SpaceVelocityFlags, Vector3f};
 
 use crate::{
     input::XrInput,
     resources::{XrFrameState, XrSession},
 };
 
-use super::{actions::XrActionSets, oculus_touch::OculusController, Hand, QuatConv, Vec3Conv};
+use super::{actions::XrActionSets, controllers::XrControllers, Hand, QuatConv, Vec3Conv};
 
 #[derive(Component)]
 pub struct OpenXRTrackingRoot;
@@ -20,6 +25,7 @@ pub struct OpenXRTracker;
 pub struct OpenXRLeftEye;
 #[derive(Component)]
 pub struct OpenXRRightEye;
+/// The users head, located from the VIEW space every frame
 #[derive(Component)]
 pub struct OpenXRHMD;
 #[derive(Component)]
@@ -30,6 +36,161 @@ pub struct OpenXRRightController;
 pub struct OpenXRController;
 #[derive(Component)]
 pub struct AimPose(pub Transform);
+/// Pose of the palm relative to the tracking root, needs `XR_EXT_palm_pose`
+#[derive(Component)]
+pub struct PalmPose(pub Transform);
+
+/// Linear (m/s) and angular (rad/s) velocity of a tracked entity, relative to the
+/// [`OpenXRTrackingRoot`]
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq)]
+pub struct XrVelocity {
+    pub linear: Vec3,
+    pub linear_valid: bool,
+    pub angular: Vec3,
+    pub angular_valid: bool,
+}
+
+impl XrVelocity {
+    pub fn new(flags: SpaceVelocityFlags, linear: Vector3f, angular: Vector3f) -> Self {
+        Self {
+            linear: linear.to_vec3(),
+            linear_valid: flags.contains(SpaceVelocityFlags::LINEAR_VALID),
+            angular: angular.to_vec3(),
+            angular_valid: flags.contains(SpaceVelocityFlags::ANGULAR_VALID),
+        }
+    }
+    pub fn from_space_velocity(velocity: &SpaceVelocity) -> Self {
+        Self::new(
+            velocity.velocity_flags,
+            velocity.linear_velocity,
+            velocity.angular_velocity,
+        )
+    }
+    /// linear velocity in world space, `root` is the [`GlobalTransform`] of the
+    /// [`OpenXRTrackingRoot`]
+    pub fn linear_world(&self, root: &GlobalTransform) -> Vec3 {
+        root.affine().transform_vector3(self.linear)
+    }
+    /// angular velocity in world space, `root` is the [`GlobalTransform`] of the
+    /// [`OpenXRTrackingRoot`]
+    pub fn angular_world(&self, root: &GlobalTransform) -> Vec3 {
+        let (_, rotation, _) = root.to_scale_rotation_translation();
+        rotation * self.angular
+    }
+}
+
+/// Validity of the last located pose of a tracked entity, a pose can be valid but not tracked
+/// when the runtime is estimating it
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct XrTrackingStatus {
+    pub position_valid: bool,
+    pub position_tracked: bool,
+    pub orientation_valid: bool,
+    pub orientation_tracked: bool,
+}
+
+impl XrTrackingStatus {
+    pub fn from_flags(flags: SpaceLocationFlags) -> Self {
+        Self {
+            position_valid: flags.contains(SpaceLocationFlags::POSITION_VALID),
+            position_tracked: flags.contains(SpaceLocationFlags::POSITION_TRACKED),
+            orientation_valid: flags.contains(SpaceLocationFlags::ORIENTATION_VALID),
+            orientation_tracked: flags.contains(SpaceLocationFlags::ORIENTATION_TRACKED),
+        }
+    }
+    pub fn is_valid(&self) -> bool {
+        self.position_valid && self.orientation_valid
+    }
+}
+
+/// What happens to tracked entities while their pose is invalid,
+/// the last valid pose is always kept in the [`Transform`]
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TrackingLossPolicy {
+    #[default]
+    HoldLastPose,
+    /// sets the [`Visibility`] to hidden until tracking is regained
+    Hide,
+}
+
+/// Sent when the pose of a tracked entity becomes invalid
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TrackingLost {
+    pub entity: Entity,
+}
+
+/// Sent when the pose of a tracked entity becomes valid again
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TrackingRegained {
+    pub entity: Entity,
+}
+
+#[derive(SystemParam)]
+pub struct TrackingStatusParams<'w, 's> {
+    commands: Commands<'w, 's>,
+    policy: Res<'w, TrackingLossPolicy>,
+    lost: EventWriter<'w, TrackingLost>,
+    regained: EventWriter<'w, TrackingRegained>,
+}
+
+impl TrackingStatusParams<'_, '_> {
+    /// Updates or inserts the [`XrTrackingStatus`] of `entity`, sends [`TrackingLost`] and
+    /// [`TrackingRegained`] and applies the [`TrackingLossPolicy`]
+    pub fn update(
+        &mut self,
+        entity: Entity,
+        flags: SpaceLocationFlags,
+        status: Option<Mut<XrTrackingStatus>>,
+        visibility: Option<Mut<Visibility>>,
+    ) -> XrTrackingStatus {
+        self.update_status(
+            entity,
+            XrTrackingStatus::from_flags(flags),
+            status,
+            visibility,
+        )
+    }
+
+    /// Same as [`TrackingStatusParams::update`] for an already computed status. Entities
+    /// without a status count as valid, so one that starts out untracked loses tracking
+    pub fn update_status(
+        &mut self,
+        entity: Entity,
+        new_status: XrTrackingStatus,
+        status: Option<Mut<XrTrackingStatus>>,
+        visibility: Option<Mut<Visibility>>,
+    ) -> XrTrackingStatus {
+        let was_valid = status.as_ref().map_or(true, |s| s.is_valid());
+        match status {
+            Some(mut status) => {
+                status.set_if_neq(new_status);
+            }
+            None => {
+                self.commands.entity(entity).insert(new_status);
+            }
+        }
+        match (was_valid, new_status.is_valid()) {
+            (true, false) => {
+                debug!("lost tracking of {:?}", entity);
+                self.lost.send(TrackingLost { entity });
+                if let (TrackingLossPolicy::Hide, Some(mut visibility)) = (*self.policy, visibility)
+                {
+                    *visibility = Visibility::Hidden;
+                }
+            }
+            (false, true) => {
+                debug!("regained tracking of {:?}", entity);
+                self.regained.send(TrackingRegained { entity });
+                if let (TrackingLossPolicy::Hide, Some(mut visibility)) = (*self.policy, visibility)
+                {
+                    *visibility = Visibility::Inherited;
+                }
+            }
+            _ => {}
+        }
+        new_status
+    }
+}
 
 pub fn adopt_open_xr_trackers(
     query: Query<Entity, (With<OpenXRTracker>, Without<Parent>)>,
@@ -56,88 +217,123 @@ pub fn verify_quat(mut quat: Quat) -> Quat {
     quat.normalize()
 }
 
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 pub fn update_open_xr_controllers(
-    oculus_controller: Res<OculusController>,
-    mut left_controller_query: Query<
-        (&mut Transform, Option<&mut AimPose>),
-        (With<OpenXRLeftController>, Without<OpenXRRightController>),
-    >,
-    mut right_controller_query: Query<
-        (&mut Transform, Option<&mut AimPose>),
-        (With<OpenXRRightController>, Without<OpenXRLeftController>),
+    controllers: Res<XrControllers>,
+    mut controller_query: Query<
+        (
+            Entity,
+            &mut Transform,
+            Option<&mut AimPose>,
+            Option<&mut PalmPose>,
+            Option<&mut XrVelocity>,
+            Option<&mut XrTrackingStatus>,
+            Option<&mut Visibility>,
+            Has<OpenXRLeftController>,
+        ),
+        Or<(With<OpenXRLeftController>, With<OpenXRRightController>)>,
     >,
     frame_state: Res<XrFrameState>,
     xr_input: Res<XrInput>,
     session: Res<XrSession>,
     action_sets: Res<XrActionSets>,
+    mut tracking: TrackingStatusParams,
 ) {
     //get controller
-    let controller = oculus_controller.get_ref(&session, &frame_state, &xr_input, &action_sets);
-    //get left controller
-    let left_grip_space = controller.grip_space(Hand::Left);
-    let left_aim_space = controller.aim_space(Hand::Left);
-    let left_postion = left_grip_space.0.pose.position.to_vec3();
-    //TODO figure out how to not get the entity multiple times
-    let left_aim_pose = left_controller_query.get_single_mut();
-    //set aim pose
-    match left_aim_pose {
-        Ok(left_entity) => match left_entity.1 {
-            Some(mut pose) => {
-                *pose = AimPose(Transform {
-                    translation: left_aim_space.0.pose.position.to_vec3(),
-                    rotation: verify_quat(left_aim_space.0.pose.orientation.to_quat()),
-                    scale: Vec3::splat(1.0),
-                });
+    let controller = controllers.get_ref(&session, &frame_state, &xr_input, &action_sets);
+    for (entity, mut transform, aim_pose, palm_pose, velocity, status, visibility, is_left) in
+        &mut controller_query
+    {
+        let hand = match is_left {
+            true => Hand::Left,
+            false => Hand::Right,
+        };
+        let (grip, grip_velocity) = controller.grip_space(hand);
+        let status = tracking.update(entity, grip.location_flags, status, visibility);
+        //set velocity
+        if let Some(mut velocity) = velocity {
+            *velocity = XrVelocity::from_space_velocity(&grip_velocity);
+        }
+        //set aim pose
+        if let Some(mut pose) = aim_pose {
+            let (aim, _) = controller.aim_space(hand);
+            let aim_status = XrTrackingStatus::from_flags(aim.location_flags);
+            if aim_status.position_valid {
+                pose.0.translation = aim.pose.position.to_vec3();
+            }
+            if aim_status.orientation_valid {
+                pose.0.rotation = verify_quat(aim.pose.orientation.to_quat());
             }
-            None => (),
-        },
-        Err(_) => debug!("no left controlelr entity found"),
-    }
-    //set translation
-    let left_translation = left_controller_query.get_single_mut();
-    match left_translation {
-        Ok(mut left_entity) => left_entity.0.translation = left_postion,
-        Err(_) => (),
-    }
-    //set rotation
-    let left_rotataion = left_controller_query.get_single_mut();
-    match left_rotataion {
-        Ok(mut left_entity) => {
-            left_entity.0.rotation = verify_quat(left_grip_space.0.pose.orientation.to_quat())
         }
-        Err(_) => (),
-    }
-    //get right controller
-    let right_grip_space = controller.grip_space(Hand::Right);
-    let right_aim_space = controller.aim_space(Hand::Right);
-    let right_postion = right_grip_space.0.pose.position.to_vec3();
-
-    let right_aim_pose = right_controller_query.get_single_mut();
-    match right_aim_pose {
-        Ok(right_entity) => match right_entity.1 {
-            Some(mut pose) => {
-                *pose = AimPose(Transform {
-                    translation: right_aim_space.0.pose.position.to_vec3(),
-                    rotation: verify_quat(right_aim_space.0.pose.orientation.to_quat()),
-                    scale: Vec3::splat(1.0),
-                });
+        //set palm pose
+        if let Some(mut pose) = palm_pose {
+            let (palm, _) = controller.palm_space(hand);
+            let palm_status = XrTrackingStatus::from_flags(palm.location_flags);
+            if palm_status.position_valid {
+                pose.0.translation = palm.pose.position.to_vec3();
             }
-            None => (),
-        },
-        Err(_) => debug!("no right controlelr entity found"),
+            if palm_status.orientation_valid {
+                pose.0.rotation = verify_quat(palm.pose.orientation.to_quat());
+            }
+        }
+        //set translation and rotation, invalid poses keep the last valid one
+        if status.position_valid {
+            transform.translation = grip.pose.position.to_vec3();
+        }
+        if status.orientation_valid {
+            transform.rotation = verify_quat(grip.pose.orientation.to_quat());
+        }
     }
-    //set translation
-    let right_translation = right_controller_query.get_single_mut();
-    match right_translation {
-        Ok(mut right_entity) => right_entity.0.translation = right_postion,
-        Err(_) => (),
+}
+
+/// Spawns the [`OpenXRHMD`] entity, it gets parented to the [`OpenXRTrackingRoot`] like any
+/// other [`OpenXRTracker`] and is despawned together with it
+pub fn spawn_open_xr_hmd(mut commands: Commands, hmd_query: Query<(), With<OpenXRHMD>>) {
+    if hmd_query.is_empty() {
+        commands.spawn((
+            SpatialBundle::default(),
+            Name::new("OpenXR HMD"),
+            OpenXRTracker,
+            OpenXRHMD,
+            XrVelocity::default(),
+        ));
     }
-    //set rotation
-    let right_rotataion = right_controller_query.get_single_mut();
-    match right_rotataion {
-        Ok(mut right_entity) => {
-            right_entity.0.rotation = verify_quat(right_grip_space.0.pose.orientation.to_quat())
+}
+
+/// Locates the [`OpenXRHMD`] from the VIEW space at the predicted display time
+#[allow(clippy::type_complexity)]
+pub fn update_open_xr_hmd(
+    mut hmd_query: Query<
+        (
+            Entity,
+            &mut Transform,
+            Option<&mut XrVelocity>,
+            Option<&mut XrTrackingStatus>,
+            Option<&mut Visibility>,
+        ),
+        With<OpenXRHMD>,
+    >,
+    frame_state: Res<XrFrameState>,
+    xr_input: Res<XrInput>,
+    mut tracking: TrackingStatusParams,
+) {
+    let (location, velocity) = match xr_input
+        .head
+        .relate(&xr_input.stage, frame_state.predicted_display_time)
+    {
+        Ok(relation) => relation,
+        Err(_) => (SpaceLocation::default(), SpaceVelocity::default()),
+    };
+    for (entity, mut transform, hmd_velocity, status, visibility) in &mut hmd_query {
+        let status = tracking.update(entity, location.location_flags, status, visibility);
+        if let Some(mut hmd_velocity) = hmd_velocity {
+            *hmd_velocity = XrVelocity::from_space_velocity(&velocity);
+        }
+        if status.position_valid {
+            transform.translation = location.pose.position.to_vec3();
+        }
+        if status.orientation_valid {
+            transform.rotation = verify_quat(location.pose.orientation.to_quat());
         }
-        Err(_) => (),
     }
 }
@@ -0,0 +1,451 @@
+use std::ptr;
+
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use openxr as xr;
+
+use crate::{
+    cvt,
+    input::XrInput,
+    resources::{XrFrameState, XrInstance, XrSession},
+    xr_init::{xr_only, XrCleanup, XrPostSetup, XrSetup},
+};
+
+use super::{
+    actions::{ActionHandednes, ActionType, SetupActionSets, XrActionSets, XrBinding},
+    trackers::{verify_quat, OpenXRTracker, TrackingStatusParams, XrTrackingStatus, XrVelocity},
+    QuatConv, Vec3Conv,
+};
+
+const VIVE_TRACKER_ACTION_SET: &str = "vive_trackers";
+const VIVE_TRACKER_PROFILE: &str = "/interaction_profiles/htc/vive_tracker_htcx";
+const VIVE_WRIST_TRACKER_PROFILE: &str = "/interaction_profiles/htc/vive_wrist_tracker";
+
+/// Spawns a tracked entity with a [`TrackerRole`] for every connected Vive tracker, needs
+/// `XR_HTCX_vive_tracker_interaction` and optionally `XR_HTC_vive_wrist_tracker_interaction`.
+/// The tracker paths are enumerated again on every [`XrViveTrackerConnected`]
+pub struct ViveTrackerPlugin;
+
+impl Plugin for ViveTrackerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<TrackerConnected>();
+        app.add_event::<TrackerDisconnected>();
+        app.add_systems(XrSetup, setup_vive_tracker_action_set);
+        app.add_systems(XrPostSetup, setup_vive_tracker_spaces);
+        app.add_systems(
+            Update,
+            (update_connected_vive_trackers, update_vive_trackers)
+                .chain()
+                .run_if(resource_exists::<ViveTrackers>)
+                .run_if(xr_only()),
+        );
+        app.add_systems(XrCleanup, cleanup_vive_trackers);
+    }
+}
+
+/// The role assigned to a tracker in the runtime, trackers without a role or with a role that
+/// isn't listed here are only exposed through [`ViveTrackers::paths`]
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Hash, Reflect)]
+pub enum TrackerRole {
+    HandheldObject,
+    LeftFoot,
+    RightFoot,
+    LeftShoulder,
+    RightShoulder,
+    LeftElbow,
+    RightElbow,
+    LeftKnee,
+    RightKnee,
+    Waist,
+    Chest,
+    Camera,
+    Keyboard,
+    /// `XR_HTC_vive_wrist_tracker_interaction`
+    LeftWrist,
+    /// `XR_HTC_vive_wrist_tracker_interaction`
+    RightWrist,
+}
+
+impl TrackerRole {
+    pub fn all() -> [TrackerRole; 15] {
+        [
+            TrackerRole::HandheldObject,
+            TrackerRole::LeftFoot,
+            TrackerRole::RightFoot,
+            TrackerRole::LeftShoulder,
+            TrackerRole::RightShoulder,
+            TrackerRole::LeftElbow,
+            TrackerRole::RightElbow,
+            TrackerRole::LeftKnee,
+            TrackerRole::RightKnee,
+            TrackerRole::Waist,
+            TrackerRole::Chest,
+            TrackerRole::Camera,
+            TrackerRole::Keyboard,
+            TrackerRole::LeftWrist,
+            TrackerRole::RightWrist,
+        ]
+    }
+    pub fn user_path(&self) -> &'static str {
+        match self {
+            TrackerRole::HandheldObject => "/user/vive_tracker_htcx/role/handheld_object",
+            TrackerRole::LeftFoot => "/user/vive_tracker_htcx/role/left_foot",
+            TrackerRole::RightFoot => "/user/vive_tracker_htcx/role/right_foot",
+            TrackerRole::LeftShoulder => "/user/vive_tracker_htcx/role/left_shoulder",
+            TrackerRole::RightShoulder => "/user/vive_tracker_htcx/role/right_shoulder",
+            TrackerRole::LeftElbow => "/user/vive_tracker_htcx/role/left_elbow",
+            TrackerRole::RightElbow => "/user/vive_tracker_htcx/role/right_elbow",
+            TrackerRole::LeftKnee => "/user/vive_tracker_htcx/role/left_knee",
+            TrackerRole::RightKnee => "/user/vive_tracker_htcx/role/right_knee",
+            TrackerRole::Waist => "/user/vive_tracker_htcx/role/waist",
+            TrackerRole::Chest => "/user/vive_tracker_htcx/role/chest",
+            TrackerRole::Camera => "/user/vive_tracker_htcx/role/camera",
+            TrackerRole::Keyboard => "/user/vive_tracker_htcx/role/keyboard",
+            TrackerRole::LeftWrist => "/user/wrist_htc/left",
+            TrackerRole::RightWrist => "/user/wrist_htc/right",
+        }
+    }
+    /// the role with the `/user/vive_tracker_htcx/role/*` or `/user/wrist_htc/*` path
+    pub fn from_user_path(path: &str) -> Option<TrackerRole> {
+        TrackerRole::all()
+            .into_iter()
+            .find(|role| role.user_path() == path)
+    }
+    pub fn is_wrist(&self) -> bool {
+        matches!(self, TrackerRole::LeftWrist | TrackerRole::RightWrist)
+    }
+    fn action_name(&self) -> &'static str {
+        match self {
+            TrackerRole::HandheldObject => "handheld_object_pose",
+            TrackerRole::LeftFoot => "left_foot_pose",
+            TrackerRole::RightFoot => "right_foot_pose",
+            TrackerRole::LeftShoulder => "left_shoulder_pose",
+            TrackerRole::RightShoulder => "right_shoulder_pose",
+            TrackerRole::LeftElbow => "left_elbow_pose",
+            TrackerRole::RightElbow => "right_elbow_pose",
+            TrackerRole::LeftKnee => "left_knee_pose",
+            TrackerRole::RightKnee => "right_knee_pose",
+            TrackerRole::Waist => "waist_pose",
+            TrackerRole::Chest => "chest_pose",
+            TrackerRole::Camera => "camera_pose",
+            TrackerRole::Keyboard => "keyboard_pose",
+            TrackerRole::LeftWrist => "left_wrist_pose",
+            TrackerRole::RightWrist => "right_wrist_pose",
+        }
+    }
+    fn binding_path(&self) -> &'static str {
+        match self {
+            TrackerRole::HandheldObject => {
+                "/user/vive_tracker_htcx/role/handheld_object/input/grip/pose"
+            }
+            TrackerRole::LeftFoot => "/user/vive_tracker_htcx/role/left_foot/input/grip/pose",
+            TrackerRole::RightFoot => "/user/vive_tracker_htcx/role/right_foot/input/grip/pose",
+            TrackerRole::LeftShoulder => {
+                "/user/vive_tracker_htcx/role/left_shoulder/input/grip/pose"
+            }
+            TrackerRole::RightShoulder => {
+                "/user/vive_tracker_htcx/role/right_shoulder/input/grip/pose"
+            }
+            TrackerRole::LeftElbow => "/user/vive_tracker_htcx/role/left_elbow/input/grip/pose",
+            TrackerRole::RightElbow => "/user/vive_tracker_htcx/role/right_elbow/input/grip/pose",
+            TrackerRole::LeftKnee => "/user/vive_tracker_htcx/role/left_knee/input/grip/pose",
+            TrackerRole::RightKnee => "/user/vive_tracker_htcx/role/right_knee/input/grip/pose",
+            TrackerRole::Waist => "/user/vive_tracker_htcx/role/waist/input/grip/pose",
+            TrackerRole::Chest => "/user/vive_tracker_htcx/role/chest/input/grip/pose",
+            TrackerRole::Camera => "/user/vive_tracker_htcx/role/camera/input/grip/pose",
+            TrackerRole::Keyboard => "/user/vive_tracker_htcx/role/keyboard/input/grip/pose",
+            TrackerRole::LeftWrist => "/user/wrist_htc/left/input/entity_htc/pose",
+            TrackerRole::RightWrist => "/user/wrist_htc/right/input/entity_htc/pose",
+        }
+    }
+}
+
+/// Sent by `xr_poll_events` for every `XrEventDataViveTrackerConnectedHTCX`
+#[derive(Event, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct XrViveTrackerConnected;
+
+/// A tracker returned by `xrEnumerateViveTrackerPathsHTCX`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ViveTrackerPaths {
+    /// unique path of the tracker, like `/devices/htc/vive_tracker_htcx/serial/<serial>`
+    pub persistent_path: xr::Path,
+    /// [`xr::Path::NULL`] when no role is assigned in the runtime
+    pub role_path: xr::Path,
+    /// the role behind `role_path`, `None` when no role or an unknown one is assigned
+    pub role: Option<TrackerRole>,
+}
+
+/// Sent when a tracker with `role` becomes active
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TrackerConnected {
+    pub role: TrackerRole,
+    pub entity: Entity,
+}
+
+/// Sent when a tracker with `role` is no longer active, its entity was despawned
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TrackerDisconnected {
+    pub role: TrackerRole,
+}
+
+struct ViveTracker {
+    space: xr::Space,
+    entity: Option<Entity>,
+}
+
+#[derive(Resource)]
+pub struct ViveTrackers {
+    trackers: HashMap<TrackerRole, ViveTracker>,
+    paths: Vec<ViveTrackerPaths>,
+}
+
+impl ViveTrackers {
+    /// the entity of the tracker with `role`, if it is connected
+    pub fn get(&self, role: TrackerRole) -> Option<Entity> {
+        self.trackers.get(&role).and_then(|t| t.entity)
+    }
+    pub fn connected(&self) -> impl Iterator<Item = (TrackerRole, Entity)> + '_ {
+        self.trackers
+            .iter()
+            .filter_map(|(role, tracker)| tracker.entity.map(|e| (*role, e)))
+    }
+    /// every tracker of the last enumeration, including the ones without an entity because they
+    /// have no role or an unknown one. Wrist trackers are never enumerated
+    pub fn paths(&self) -> &[ViveTrackerPaths] {
+        &self.paths
+    }
+}
+
+/// the connected trackers with their roles, empty without `XR_HTCX_vive_tracker_interaction`
+fn enumerate_vive_tracker_paths(instance: &XrInstance) -> xr::Result<Vec<ViveTrackerPaths>> {
+    let fp = match instance.exts().htcx_vive_tracker_interaction {
+        Some(fp) => fp,
+        None => return Ok(Vec::new()),
+    };
+    let mut count = 0;
+    unsafe {
+        cvt((fp.enumerate_vive_tracker_paths)(
+            instance.as_raw(),
+            0,
+            &mut count,
+            ptr::null_mut(),
+        ))?;
+    }
+    let mut paths = vec![
+        xr::sys::ViveTrackerPathsHTCX {
+            ty: xr::sys::ViveTrackerPathsHTCX::TYPE,
+            next: ptr::null_mut(),
+            persistent_path: xr::Path::NULL,
+            role_path: xr::Path::NULL,
+        };
+        count as usize
+    ];
+    unsafe {
+        cvt((fp.enumerate_vive_tracker_paths)(
+            instance.as_raw(),
+            paths.len() as u32,
+            &mut count,
+            paths.as_mut_ptr(),
+        ))?;
+    }
+    paths.truncate(count as usize);
+    Ok(paths
+        .into_iter()
+        .map(|paths| ViveTrackerPaths {
+            persistent_path: paths.persistent_path,
+            role_path: paths.role_path,
+            role: match paths.role_path == xr::Path::NULL {
+                true => None,
+                false => instance
+                    .path_to_string(paths.role_path)
+                    .ok()
+                    .and_then(|path| TrackerRole::from_user_path(&path)),
+            },
+        })
+        .collect())
+}
+
+fn enabled_roles(instance: &XrInstance) -> Vec<TrackerRole> {
+    let exts = instance.exts();
+    TrackerRole::all()
+        .into_iter()
+        .filter(|role| match role.is_wrist() {
+            true => exts.htc_vive_wrist_tracker_interaction.is_some(),
+            false => exts.htcx_vive_tracker_interaction.is_some(),
+        })
+        .collect()
+}
+
+fn setup_vive_tracker_action_set(
+    instance: Res<XrInstance>,
+    mut action_sets: ResMut<SetupActionSets>,
+) {
+    let roles = enabled_roles(&instance);
+    if roles.is_empty() {
+        return;
+    }
+    let action_set = action_sets.add_action_set(VIVE_TRACKER_ACTION_SET, "Vive Trackers".into(), 0);
+    for role in roles {
+        action_set.new_action(
+            role.action_name(),
+            format!("{:?} Tracker Pose", role),
+            ActionType::PoseF,
+            ActionHandednes::Single,
+        );
+        action_set.suggest_binding(
+            match role.is_wrist() {
+                true => VIVE_WRIST_TRACKER_PROFILE,
+                false => VIVE_TRACKER_PROFILE,
+            },
+            &[XrBinding::new(role.action_name(), role.binding_path())],
+        );
+    }
+}
+
+fn setup_vive_tracker_spaces(
+    mut commands: Commands,
+    instance: Res<XrInstance>,
+    action_sets: Res<XrActionSets>,
+    session: Res<XrSession>,
+) {
+    let mut trackers = HashMap::new();
+    for role in enabled_roles(&instance) {
+        let action = match action_sets.get_action_posef(VIVE_TRACKER_ACTION_SET, role.action_name())
+        {
+            Ok(a) => a,
+            Err(err) => {
+                warn!("unable to get {:?} tracker action: {}", role, err);
+                continue;
+            }
+        };
+        match action.create_space(
+            xr::Session::<xr::AnyGraphics>::clone(&session),
+            xr::Path::NULL,
+            xr::Posef::IDENTITY,
+        ) {
+            Ok(space) => {
+                trackers.insert(
+                    role,
+                    ViveTracker {
+                        space,
+                        entity: None,
+                    },
+                );
+            }
+            Err(err) => warn!("unable to create {:?} tracker space: {}", role, err),
+        }
+    }
+    if !trackers.is_empty() {
+        let paths = enumerate_vive_tracker_paths(&instance).unwrap_or_else(|err| {
+            warn!("unable to enumerate vive tracker paths: {}", err);
+            Vec::new()
+        });
+        commands.insert_resource(ViveTrackers { trackers, paths });
+    }
+}
+
+fn cleanup_vive_trackers(mut commands: Commands, tracker_query: Query<Entity, With<TrackerRole>>) {
+    for e in &tracker_query {
+        commands.entity(e).despawn_recursive();
+    }
+    commands.remove_resource::<ViveTrackers>();
+}
+
+/// Enumerates the tracker paths when a tracker connects, and spawns and despawns the tracker
+/// entities as their roles get enumerated and their actions become (in)active
+#[allow(clippy::too_many_arguments)]
+fn update_connected_vive_trackers(
+    mut commands: Commands,
+    mut trackers: ResMut<ViveTrackers>,
+    mut tracker_events: EventReader<XrViveTrackerConnected>,
+    instance: Res<XrInstance>,
+    action_sets: Res<XrActionSets>,
+    session: Res<XrSession>,
+    mut connected: EventWriter<TrackerConnected>,
+    mut disconnected: EventWriter<TrackerDisconnected>,
+) {
+    let trackers = &mut *trackers;
+    if !tracker_events.is_empty() {
+        tracker_events.clear();
+        match enumerate_vive_tracker_paths(&instance) {
+            Ok(paths) => trackers.paths = paths,
+            Err(err) => warn!("unable to enumerate vive tracker paths: {}", err),
+        }
+    }
+    for (role, tracker) in trackers.trackers.iter_mut() {
+        // wrist trackers aren't enumerated, only their action tells if they are connected
+        let enumerated = role.is_wrist() || trackers.paths.iter().any(|p| p.role == Some(*role));
+        let active = enumerated
+            && action_sets
+                .get_action_posef(VIVE_TRACKER_ACTION_SET, role.action_name())
+                .ok()
+                .and_then(|a| a.is_active(&session, xr::Path::NULL).ok())
+                .unwrap_or(false);
+        match (active, tracker.entity) {
+            (true, None) => {
+                info!("{:?} tracker connected", role);
+                let entity = commands
+                    .spawn((
+                        SpatialBundle::default(),
+                        Name::new(format!("{:?} Tracker", role)),
+                        OpenXRTracker,
+                        *role,
+                        XrVelocity::default(),
+                    ))
+                    .id();
+                tracker.entity = Some(entity);
+                connected.send(TrackerConnected {
+                    role: *role,
+                    entity,
+                });
+            }
+            (false, Some(entity)) => {
+                info!("{:?} tracker disconnected", role);
+                commands.entity(entity).despawn_recursive();
+                tracker.entity = None;
+                disconnected.send(TrackerDisconnected { role: *role });
+            }
+            _ => {}
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn update_vive_trackers(
+    trackers: Res<ViveTrackers>,
+    mut tracker_query: Query<(
+        Entity,
+        &TrackerRole,
+        &mut Transform,
+        Option<&mut XrVelocity>,
+        Option<&mut XrTrackingStatus>,
+        Option<&mut Visibility>,
+    )>,
+    frame_state: Res<XrFrameState>,
+    xr_input: Res<XrInput>,
+    mut tracking: TrackingStatusParams,
+) {
+    for (entity, role, mut transform, velocity, status, visibility) in &mut tracker_query {
+        let tracker = match trackers.trackers.get(role) {
+            Some(t) => t,
+            None => continue,
+        };
+        let (location, tracker_velocity) = match tracker
+            .space
+            .relate(&xr_input.stage, frame_state.predicted_display_time)
+        {
+            Ok(relation) => relation,
+            Err(_) => (xr::SpaceLocation::default(), xr::SpaceVelocity::default()),
+        };
+        let status = tracking.update(entity, location.location_flags, status, visibility);
+        if let Some(mut velocity) = velocity {
+            *velocity = XrVelocity::from_space_velocity(&tracker_velocity);
+        }
+        if status.position_valid {
+            transform.translation = location.pose.position.to_vec3();
+        }
+        if status.orientation_valid {
+            transform.rotation = verify_quat(location.pose.orientation.to_quat());
+        }
+    }
+}